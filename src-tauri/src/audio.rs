@@ -0,0 +1,388 @@
+use std::io::Cursor;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use serde::Serialize;
+
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::{DecoderOptions, CODEC_TYPE_NULL};
+use symphonia::core::errors::Error as SymphoniaError;
+use symphonia::core::formats::FormatOptions;
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+use tauri::ipc::{InvokeBody, Request};
+use tauri::AppHandle;
+
+use crate::error::{run_blocking, AppError};
+use crate::map_cache;
+
+// ---------------------------------------------------------------------------
+// Audio decoding (symphonia)
+// ---------------------------------------------------------------------------
+
+/// Header carrying the file extension of a raw audio request body.
+pub const EXTENSION_HEADER: &str = "x-audio-extension";
+/// WAV copy of a map's audio, for webviews that can't decode the original.
+pub const TRANSCODED_FILENAME: &str = "audio.transcoded.wav";
+
+/// Duration and format of a decoded file.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AudioMetadata {
+    /// Symphonia's short codec name, e.g. `vorbis`, `mp3`, `flac`, `pcm_s16le`.
+    pub codec: String,
+    pub sample_rate: u32,
+    pub channels: usize,
+    /// Samples per channel.
+    pub frames: u64,
+    pub duration_secs: f64,
+}
+
+/// Interleaved PCM with every channel kept.
+#[derive(Debug, Clone)]
+pub struct Pcm {
+    pub samples: Vec<f32>,
+    pub channels: usize,
+    pub sample_rate: u32,
+    pub codec: String,
+}
+
+impl Pcm {
+    pub fn frames(&self) -> usize {
+        self.samples.len() / self.channels
+    }
+
+    pub fn metadata(&self) -> AudioMetadata {
+        AudioMetadata {
+            codec: self.codec.clone(),
+            sample_rate: self.sample_rate,
+            channels: self.channels,
+            frames: self.frames() as u64,
+            duration_secs: self.frames() as f64 / self.sample_rate as f64,
+        }
+    }
+
+    pub fn into_mono(self) -> DecodedAudio {
+        let samples = if self.channels == 1 {
+            self.samples
+        } else {
+            self.samples
+                .chunks_exact(self.channels)
+                .map(|frame| frame.iter().sum::<f32>() / self.channels as f32)
+                .collect()
+        };
+        DecodedAudio {
+            samples,
+            sample_rate: self.sample_rate,
+        }
+    }
+
+    /// Encode as a 16-bit PCM WAV file.
+    pub fn to_wav(&self) -> Result<Vec<u8>, AppError> {
+        let data_len = u32::try_from(self.samples.len() * 2)
+            .ok()
+            .filter(|len| *len <= u32::MAX - 36)
+            .ok_or_else(|| AppError::invalid_argument("Audio is too long for a WAV file"))?;
+        let channels = self.channels as u16;
+        let block_align = channels * 2;
+
+        let mut wav = Vec::with_capacity(44 + data_len as usize);
+        wav.extend_from_slice(b"RIFF");
+        wav.extend_from_slice(&(36 + data_len).to_le_bytes());
+        wav.extend_from_slice(b"WAVEfmt ");
+        wav.extend_from_slice(&16u32.to_le_bytes());
+        wav.extend_from_slice(&1u16.to_le_bytes());
+        wav.extend_from_slice(&channels.to_le_bytes());
+        wav.extend_from_slice(&self.sample_rate.to_le_bytes());
+        wav.extend_from_slice(&(self.sample_rate * block_align as u32).to_le_bytes());
+        wav.extend_from_slice(&block_align.to_le_bytes());
+        wav.extend_from_slice(&16u16.to_le_bytes());
+        wav.extend_from_slice(b"data");
+        wav.extend_from_slice(&data_len.to_le_bytes());
+        for sample in &self.samples {
+            let value = (sample.clamp(-1.0, 1.0) * i16::MAX as f32).round() as i16;
+            wav.extend_from_slice(&value.to_le_bytes());
+        }
+        Ok(wav)
+    }
+}
+
+/// Mono PCM, channels averaged.
+#[derive(Debug, Clone)]
+pub struct DecodedAudio {
+    pub samples: Vec<f32>,
+    pub sample_rate: u32,
+}
+
+impl DecodedAudio {
+    pub fn duration_secs(&self) -> f64 {
+        self.samples.len() as f64 / self.sample_rate as f64
+    }
+}
+
+fn decode_error(e: SymphoniaError) -> AppError {
+    AppError::audio_decode(format!("Failed to decode audio: {}", e))
+}
+
+/// Decode a complete audio file (Ogg Vorbis, MP3, WAV or FLAC). `extension`
+/// only guides format probing; the contents decide.
+pub fn decode(bytes: Vec<u8>, extension: Option<&str>) -> Result<Pcm, AppError> {
+    let mut hint = Hint::new();
+    if let Some(ext) = extension {
+        // Beat Saber ships Ogg Vorbis as .egg
+        hint.with_extension(if ext.eq_ignore_ascii_case("egg") { "ogg" } else { ext });
+    }
+    let stream = MediaSourceStream::new(Box::new(Cursor::new(bytes)), Default::default());
+    let probed = symphonia::default::get_probe()
+        .format(&hint, stream, &FormatOptions::default(), &MetadataOptions::default())
+        .map_err(decode_error)?;
+    let mut format = probed.format;
+
+    let track = format
+        .tracks()
+        .iter()
+        .find(|t| t.codec_params.codec != CODEC_TYPE_NULL)
+        .ok_or_else(|| AppError::audio_decode("No audio track found"))?;
+    let track_id = track.id;
+    let codecs = symphonia::default::get_codecs();
+    let codec = codecs
+        .get_codec(track.codec_params.codec)
+        .map_or("unknown", |descriptor| descriptor.short_name)
+        .to_string();
+    let mut decoder = codecs
+        .make(&track.codec_params, &DecoderOptions::default())
+        .map_err(decode_error)?;
+
+    let mut samples = Vec::new();
+    let mut sample_rate = track.codec_params.sample_rate.unwrap_or(0);
+    let mut channels = 0;
+    loop {
+        let packet = match format.next_packet() {
+            Ok(packet) => packet,
+            Err(SymphoniaError::IoError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(decode_error(e)),
+        };
+        if packet.track_id() != track_id {
+            continue;
+        }
+        let decoded = match decoder.decode(&packet) {
+            Ok(decoded) => decoded,
+            // A corrupt packet is skipped rather than failing the whole file
+            Err(SymphoniaError::DecodeError(_)) => continue,
+            Err(e) => return Err(decode_error(e)),
+        };
+
+        let spec = *decoded.spec();
+        let packet_channels = spec.channels.count().max(1);
+        if channels == 0 {
+            channels = packet_channels;
+        } else if packet_channels != channels {
+            return Err(AppError::audio_decode("Channel count changes mid-stream"));
+        }
+        sample_rate = spec.rate;
+        let mut buf = SampleBuffer::<f32>::new(decoded.capacity() as u64, spec);
+        buf.copy_interleaved_ref(decoded);
+        samples.extend_from_slice(buf.samples());
+    }
+
+    if sample_rate == 0 || samples.is_empty() {
+        return Err(AppError::audio_decode("Audio file contains no samples"));
+    }
+    Ok(Pcm {
+        samples,
+        channels,
+        sample_rate,
+        codec,
+    })
+}
+
+/// Decode a complete audio file to mono PCM, for analysis.
+pub fn decode_mono(bytes: Vec<u8>, extension: Option<&str>) -> Result<DecodedAudio, AppError> {
+    decode(bytes, extension).map(Pcm::into_mono)
+}
+
+fn extension_of(filename: &str) -> Option<&str> {
+    filename.rsplit_once('.').map(|(_, ext)| ext)
+}
+
+/// Decode the audio of a downloaded map.
+pub fn decode_map_audio(app_handle: &AppHandle, map_id: &str) -> Result<Pcm, AppError> {
+    let (bytes, filename) = map_cache::read_audio(app_handle, map_id)?;
+    decode(bytes, extension_of(&filename))
+}
+
+/// Serializes transcodes, so concurrent requests for the same map (e.g. both
+/// players of a duel) decode it once.
+static TRANSCODE_LOCK: Mutex<()> = Mutex::new(());
+
+/// Path of a WAV copy of a downloaded map's audio, transcoding it on first use.
+pub fn transcoded_map_audio(app_handle: &AppHandle, map_id: &str) -> Result<PathBuf, AppError> {
+    let _guard = TRANSCODE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let path = map_cache::derived_path(app_handle, map_id, TRANSCODED_FILENAME)?;
+    cached_transcode(
+        path,
+        || decode_map_audio(app_handle, map_id),
+        |wav| map_cache::write_derived(app_handle, map_id, TRANSCODED_FILENAME, wav),
+    )
+}
+
+/// `path` if a transcode is already stored there, otherwise `decode` encoded
+/// as WAV and stored by `write`.
+fn cached_transcode(
+    path: PathBuf,
+    decode: impl FnOnce() -> Result<Pcm, AppError>,
+    write: impl FnOnce(&[u8]) -> Result<PathBuf, AppError>,
+) -> Result<PathBuf, AppError> {
+    if path.exists() {
+        return Ok(path);
+    }
+    write(&decode()?.to_wav()?)
+}
+
+/// A raw request's header value, if present and valid text.
+pub fn header<'a>(request: &'a Request<'_>, name: &str) -> Option<&'a str> {
+    request.headers().get(name).and_then(|v| v.to_str().ok())
+}
+
+/// Audio file bytes sent as a raw invoke body, with the optional extension
+/// header.
+pub fn request_audio(request: &Request<'_>) -> Result<(Vec<u8>, Option<String>), AppError> {
+    let InvokeBody::Raw(bytes) = request.body() else {
+        return Err(AppError::invalid_argument("Expected the audio file as a raw request body"));
+    };
+    let extension = header(request, EXTENSION_HEADER).map(str::to_string);
+    Ok((bytes.clone(), extension))
+}
+
+// ---------------------------------------------------------------------------
+// Tauri commands
+// ---------------------------------------------------------------------------
+
+/// Decode a user-picked file (raw body) and report its format and duration.
+#[tauri::command]
+pub async fn audio_probe(request: Request<'_>) -> Result<AudioMetadata, AppError> {
+    let (bytes, extension) = request_audio(&request)?;
+    run_blocking(move || decode(bytes, extension.as_deref()).map(|pcm| pcm.metadata())).await
+}
+
+/// Decode a downloaded map's audio and report its format and duration.
+#[tauri::command]
+pub async fn audio_probe_map(app_handle: AppHandle, map_id: String) -> Result<AudioMetadata, AppError> {
+    run_blocking(move || decode_map_audio(&app_handle, &map_id).map(|pcm| pcm.metadata())).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stereo() -> Pcm {
+        Pcm {
+            samples: vec![0.0, 0.5, -0.5, 1.0, 0.25, -1.0],
+            channels: 2,
+            sample_rate: 22050,
+            codec: "test".into(),
+        }
+    }
+
+    fn u16_at(bytes: &[u8], at: usize) -> u16 {
+        u16::from_le_bytes([bytes[at], bytes[at + 1]])
+    }
+
+    fn u32_at(bytes: &[u8], at: usize) -> u32 {
+        u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap())
+    }
+
+    #[test]
+    fn wav_header_describes_the_samples() {
+        let wav = stereo().to_wav().unwrap();
+        assert_eq!(wav.len(), 44 + 12);
+        assert_eq!(&wav[0..4], b"RIFF");
+        assert_eq!(u32_at(&wav, 4), 36 + 12);
+        assert_eq!(&wav[8..16], b"WAVEfmt ");
+        assert_eq!(u32_at(&wav, 16), 16);
+        assert_eq!(u16_at(&wav, 20), 1);
+        assert_eq!(u16_at(&wav, 22), 2);
+        assert_eq!(u32_at(&wav, 24), 22050);
+        assert_eq!(u32_at(&wav, 28), 22050 * 4);
+        assert_eq!(u16_at(&wav, 32), 4);
+        assert_eq!(u16_at(&wav, 34), 16);
+        assert_eq!(&wav[36..40], b"data");
+        assert_eq!(u32_at(&wav, 40), 12);
+        assert_eq!(u16_at(&wav, 44 + 6) as i16, i16::MAX);
+    }
+
+    #[test]
+    fn out_of_range_samples_are_clipped() {
+        let pcm = Pcm {
+            samples: vec![2.0, -3.0],
+            channels: 1,
+            sample_rate: 8000,
+            codec: "test".into(),
+        };
+        let wav = pcm.to_wav().unwrap();
+        assert_eq!(u16_at(&wav, 44) as i16, i16::MAX);
+        assert_eq!(u16_at(&wav, 46) as i16, -i16::MAX);
+    }
+
+    #[test]
+    fn wav_round_trips_through_decode() {
+        let original = stereo();
+        let pcm = decode(original.to_wav().unwrap(), Some("wav")).unwrap();
+        assert_eq!(pcm.codec, "pcm_s16le");
+        assert_eq!(pcm.channels, 2);
+        assert_eq!(pcm.sample_rate, 22050);
+        assert_eq!(pcm.samples.len(), original.samples.len());
+        for (decoded, expected) in pcm.samples.iter().zip(&original.samples) {
+            assert!((decoded - expected).abs() < 1e-3, "{} vs {}", decoded, expected);
+        }
+
+        let metadata = pcm.metadata();
+        assert_eq!(metadata.frames, 3);
+        assert!((metadata.duration_secs - 3.0 / 22050.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn decode_mono_averages_channels() {
+        let mono = decode_mono(stereo().to_wav().unwrap(), None).unwrap();
+        assert_eq!(mono.samples.len(), 3);
+        assert!((mono.samples[0] - 0.25).abs() < 1e-3);
+        assert!((mono.samples[1] - 0.25).abs() < 1e-3);
+        assert!((mono.samples[2] + 0.375).abs() < 1e-3);
+    }
+
+    #[test]
+    fn undecodable_bytes_are_an_audio_error() {
+        for bytes in [Vec::new(), b"definitely not audio".to_vec()] {
+            let err = decode(bytes, Some("egg")).unwrap_err();
+            assert!(matches!(err, AppError::AudioDecode { .. }), "{:?}", err);
+        }
+    }
+
+    #[test]
+    fn transcode_is_written_once_then_reused() {
+        let path = std::env::temp_dir().join(format!("moontapper-transcode-{}.wav", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let write = |wav: &[u8]| {
+            std::fs::write(&path, wav)?;
+            Ok(path.clone())
+        };
+
+        let written = cached_transcode(path.clone(), || Ok(stereo()), write).unwrap();
+        assert_eq!(written, path);
+        assert_eq!(std::fs::read(&path).unwrap(), stereo().to_wav().unwrap());
+
+        let reused = cached_transcode(
+            path.clone(),
+            || panic!("a cached transcode must not be decoded again"),
+            |_: &[u8]| panic!("a cached transcode must not be rewritten"),
+        )
+        .unwrap();
+        assert_eq!(reused, path);
+
+        let _ = std::fs::remove_file(&path);
+        let err = cached_transcode(path.clone(), || Err(AppError::audio_decode("bad")), write).unwrap_err();
+        assert!(matches!(err, AppError::AudioDecode { .. }));
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,549 @@
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+use crate::error::{run_blocking, AppError};
+use crate::map_cache;
+use crate::map_info::{self, MapInfo};
+
+// ---------------------------------------------------------------------------
+// Normalized beatmap model (v2, v3 and v4 difficulty files)
+// ---------------------------------------------------------------------------
+//
+// Mirrors the TypeScript `BeatMap`: `time` and `duration` are in seconds at
+// the Info.dat BPM. Columns are clamped to 0-3 and rows to 0-2 like the
+// TypeScript adapter.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BeatmapFormat {
+    V2,
+    V3,
+    V4,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NoteType {
+    Left,
+    Right,
+    Bomb,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BeatMapNote {
+    pub time: f64,
+    pub column: u8,
+    pub row: u8,
+    #[serde(rename = "type")]
+    pub note_type: NoteType,
+    /// Cut direction; bombs use 8 (any).
+    pub direction: u8,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BeatMapObstacle {
+    pub time: f64,
+    /// Seconds.
+    pub duration: f64,
+    pub column: u8,
+    pub width: u8,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BeatMap {
+    /// Source file version; `None` for generated charts.
+    #[serde(default)]
+    pub format: Option<BeatmapFormat>,
+    /// Sorted by time.
+    pub notes: Vec<BeatMapNote>,
+    /// Sorted by time.
+    pub obstacles: Vec<BeatMapObstacle>,
+    pub bpm: f64,
+}
+
+/// Direction used for bombs, which can be hit from any side.
+const ANY_DIRECTION: u8 = 8;
+
+fn column(x: i64) -> u8 {
+    x.clamp(0, 3) as u8
+}
+
+fn row(y: i64) -> u8 {
+    y.clamp(0, 2) as u8
+}
+
+/// Obstacle width in columns; at least 1, and clamped so the cast is lossless.
+fn width(w: i64) -> u8 {
+    w.clamp(1, u8::MAX as i64) as u8
+}
+
+fn direction(d: i64) -> u8 {
+    d.clamp(0, ANY_DIRECTION as i64) as u8
+}
+
+fn color_note_type(color: i64) -> NoteType {
+    if color == 0 {
+        NoteType::Left
+    } else {
+        NoteType::Right
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Raw v2 layout
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, Deserialize)]
+struct BeatmapV2 {
+    #[serde(rename = "_notes", default)]
+    notes: Vec<NoteV2>,
+    #[serde(rename = "_obstacles", default)]
+    obstacles: Vec<ObstacleV2>,
+}
+
+#[derive(Debug, Deserialize)]
+struct NoteV2 {
+    #[serde(rename = "_time", default)]
+    time: f64,
+    #[serde(rename = "_lineIndex", default)]
+    line_index: i64,
+    #[serde(rename = "_lineLayer", default)]
+    line_layer: i64,
+    #[serde(rename = "_type", default)]
+    note_type: i64,
+    #[serde(rename = "_cutDirection", default)]
+    cut_direction: i64,
+}
+
+#[derive(Debug, Deserialize)]
+struct ObstacleV2 {
+    #[serde(rename = "_time", default)]
+    time: f64,
+    #[serde(rename = "_lineIndex", default)]
+    line_index: i64,
+    #[serde(rename = "_duration", default)]
+    duration: f64,
+    #[serde(rename = "_width", default)]
+    width: i64,
+}
+
+fn from_v2(raw: BeatmapV2, bpm: f64) -> BeatMap {
+    let spb = 60.0 / bpm;
+    let notes = raw
+        .notes
+        .into_iter()
+        .map(|n| {
+            // 0 = left, 1 = right, 3 = bomb (2 was never used)
+            let note_type = match n.note_type {
+                0 => NoteType::Left,
+                1 => NoteType::Right,
+                _ => NoteType::Bomb,
+            };
+            BeatMapNote {
+                time: n.time * spb,
+                column: column(n.line_index),
+                row: row(n.line_layer),
+                direction: if note_type == NoteType::Bomb {
+                    ANY_DIRECTION
+                } else {
+                    direction(n.cut_direction)
+                },
+                note_type,
+            }
+        })
+        .collect();
+    let obstacles = raw
+        .obstacles
+        .into_iter()
+        .map(|o| BeatMapObstacle {
+            time: o.time * spb,
+            duration: o.duration * spb,
+            column: column(o.line_index),
+            width: width(o.width),
+        })
+        .collect();
+    BeatMap {
+        format: Some(BeatmapFormat::V2),
+        notes,
+        obstacles,
+        bpm,
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Raw v3 layout
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BeatmapV3 {
+    #[serde(default)]
+    color_notes: Vec<ColorNoteV3>,
+    #[serde(default)]
+    bomb_notes: Vec<BombNoteV3>,
+    #[serde(default)]
+    obstacles: Vec<ObstacleV3>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ColorNoteV3 {
+    #[serde(default)]
+    b: f64,
+    #[serde(default)]
+    x: i64,
+    #[serde(default)]
+    y: i64,
+    #[serde(default)]
+    c: i64,
+    #[serde(default)]
+    d: i64,
+}
+
+#[derive(Debug, Deserialize)]
+struct BombNoteV3 {
+    #[serde(default)]
+    b: f64,
+    #[serde(default)]
+    x: i64,
+    #[serde(default)]
+    y: i64,
+}
+
+#[derive(Debug, Deserialize)]
+struct ObstacleV3 {
+    #[serde(default)]
+    b: f64,
+    #[serde(default)]
+    x: i64,
+    #[serde(default)]
+    d: f64,
+    #[serde(default)]
+    w: i64,
+}
+
+fn from_v3(raw: BeatmapV3, bpm: f64) -> BeatMap {
+    let spb = 60.0 / bpm;
+    let colors = raw.color_notes.into_iter().map(|n| BeatMapNote {
+        time: n.b * spb,
+        column: column(n.x),
+        row: row(n.y),
+        note_type: color_note_type(n.c),
+        direction: direction(n.d),
+    });
+    let bombs = raw.bomb_notes.into_iter().map(|n| BeatMapNote {
+        time: n.b * spb,
+        column: column(n.x),
+        row: row(n.y),
+        note_type: NoteType::Bomb,
+        direction: ANY_DIRECTION,
+    });
+    let obstacles = raw
+        .obstacles
+        .into_iter()
+        .map(|o| BeatMapObstacle {
+            time: o.b * spb,
+            duration: o.d * spb,
+            column: column(o.x),
+            width: width(o.w),
+        })
+        .collect();
+    BeatMap {
+        format: Some(BeatmapFormat::V3),
+        notes: colors.chain(bombs).collect(),
+        obstacles,
+        bpm,
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Raw v4 layout: timed objects index into shared data arrays
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BeatmapV4 {
+    #[serde(default)]
+    color_notes: Vec<TimedV4>,
+    #[serde(default)]
+    color_notes_data: Vec<ColorNoteDataV4>,
+    #[serde(default)]
+    bomb_notes: Vec<TimedV4>,
+    #[serde(default)]
+    bomb_notes_data: Vec<BombNoteDataV4>,
+    #[serde(default)]
+    obstacles: Vec<TimedV4>,
+    #[serde(default)]
+    obstacles_data: Vec<ObstacleDataV4>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TimedV4 {
+    #[serde(default)]
+    b: f64,
+    #[serde(default)]
+    i: usize,
+}
+
+#[derive(Debug, Deserialize)]
+struct ColorNoteDataV4 {
+    #[serde(default)]
+    x: i64,
+    #[serde(default)]
+    y: i64,
+    #[serde(default)]
+    c: i64,
+    #[serde(default)]
+    d: i64,
+}
+
+#[derive(Debug, Deserialize)]
+struct BombNoteDataV4 {
+    #[serde(default)]
+    x: i64,
+    #[serde(default)]
+    y: i64,
+}
+
+#[derive(Debug, Deserialize)]
+struct ObstacleDataV4 {
+    #[serde(default)]
+    d: f64,
+    #[serde(default)]
+    x: i64,
+    #[serde(default)]
+    w: i64,
+}
+
+/// Resolve an object's data index, rejecting indices past the data array.
+fn indexed<'a, T>(data: &'a [T], index: usize, kind: &str) -> Result<&'a T, AppError> {
+    data.get(index).ok_or_else(|| {
+        AppError::invalid_beatmap(format!(
+            "Beatmap {} index {} is out of range ({} entries)",
+            kind,
+            index,
+            data.len()
+        ))
+    })
+}
+
+fn from_v4(raw: BeatmapV4, bpm: f64) -> Result<BeatMap, AppError> {
+    let spb = 60.0 / bpm;
+    let mut notes = Vec::with_capacity(raw.color_notes.len() + raw.bomb_notes.len());
+    for note in &raw.color_notes {
+        let data = indexed(&raw.color_notes_data, note.i, "colorNotesData")?;
+        notes.push(BeatMapNote {
+            time: note.b * spb,
+            column: column(data.x),
+            row: row(data.y),
+            note_type: color_note_type(data.c),
+            direction: direction(data.d),
+        });
+    }
+    for bomb in &raw.bomb_notes {
+        let data = indexed(&raw.bomb_notes_data, bomb.i, "bombNotesData")?;
+        notes.push(BeatMapNote {
+            time: bomb.b * spb,
+            column: column(data.x),
+            row: row(data.y),
+            note_type: NoteType::Bomb,
+            direction: ANY_DIRECTION,
+        });
+    }
+    let obstacles = raw
+        .obstacles
+        .iter()
+        .map(|o| {
+            let data = indexed(&raw.obstacles_data, o.i, "obstaclesData")?;
+            Ok(BeatMapObstacle {
+                time: o.b * spb,
+                duration: data.d * spb,
+                column: column(data.x),
+                width: width(data.w),
+            })
+        })
+        .collect::<Result<_, AppError>>()?;
+    Ok(BeatMap {
+        format: Some(BeatmapFormat::V4),
+        notes,
+        obstacles,
+        bpm,
+    })
+}
+
+// ---------------------------------------------------------------------------
+// Parsing
+// ---------------------------------------------------------------------------
+
+/// Parse a difficulty file of any supported version, timing it at `bpm`. The
+/// version is taken from `version` (v3/v4) or `_version`/`_notes` (v2).
+pub fn parse_beatmap(content: &str, bpm: f64) -> Result<BeatMap, AppError> {
+    let json: serde_json::Value = serde_json::from_str(content.trim_start_matches('\u{feff}'))
+        .map_err(|e| AppError::invalid_beatmap(format!("Beatmap is not valid JSON: {}", e)))?;
+    let version = json.get("version").and_then(|v| v.as_str()).unwrap_or_default();
+    let invalid = |e: serde_json::Error| AppError::invalid_beatmap(format!("Unrecognized beatmap: {}", e));
+
+    let mut beatmap = if version.starts_with('4') {
+        from_v4(serde_json::from_value(json).map_err(invalid)?, bpm)?
+    } else if version.starts_with('3') || json.get("colorNotes").is_some() {
+        from_v3(serde_json::from_value(json).map_err(invalid)?, bpm)
+    } else if json.get("_version").is_some() || json.get("_notes").is_some() {
+        from_v2(serde_json::from_value(json).map_err(invalid)?, bpm)
+    } else {
+        return Err(AppError::invalid_beatmap("Unrecognized beatmap format"));
+    };
+
+    beatmap.notes.sort_by(|a, b| a.time.total_cmp(&b.time));
+    beatmap.obstacles.sort_by(|a, b| a.time.total_cmp(&b.time));
+    Ok(beatmap)
+}
+
+// ---------------------------------------------------------------------------
+// Tauri commands
+// ---------------------------------------------------------------------------
+
+/// Normalized Info.dat of a downloaded map, whatever its format version.
+#[tauri::command]
+pub async fn beatmap_info(map_id: String, app_handle: AppHandle) -> Result<MapInfo, AppError> {
+    run_blocking(move || map_info::parse_info(&map_cache::read_info_dat(&app_handle, &map_id)?)).await
+}
+
+/// Parse one difficulty file of a downloaded map into notes and obstacles
+/// timed in seconds at the Info.dat BPM.
+#[tauri::command]
+pub async fn beatmap_parse(map_id: String, filename: String, app_handle: AppHandle) -> Result<BeatMap, AppError> {
+    run_blocking(move || {
+        let info = map_info::parse_info(&map_cache::read_info_dat(&app_handle, &map_id)?)?;
+        let content = map_cache::read_beatmap(&app_handle, &map_id, &filename)?;
+        parse_beatmap(&content, info.bpm)
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn summary(map: &BeatMap) -> Vec<(f64, u8, u8, NoteType, u8)> {
+        map.notes
+            .iter()
+            .map(|n| (n.time, n.column, n.row, n.note_type, n.direction))
+            .collect()
+    }
+
+    #[test]
+    fn parses_v2() {
+        let map = parse_beatmap(
+            r#"{
+                "_version": "2.2.0",
+                "_notes": [
+                    { "_time": 2, "_lineIndex": 5, "_lineLayer": 1, "_type": 1, "_cutDirection": 0 },
+                    { "_time": 1, "_lineIndex": 0, "_lineLayer": 0, "_type": 0, "_cutDirection": 1 },
+                    { "_time": 3, "_lineIndex": 2, "_lineLayer": 2, "_type": 3, "_cutDirection": 0 }
+                ],
+                "_obstacles": [{ "_time": 4, "_lineIndex": 1, "_duration": 2, "_width": 0 }]
+            }"#,
+            120.0,
+        )
+        .unwrap();
+        assert_eq!(map.format, Some(BeatmapFormat::V2));
+        assert_eq!(
+            summary(&map),
+            [
+                (0.5, 0, 0, NoteType::Left, 1),
+                (1.0, 3, 1, NoteType::Right, 0),
+                (1.5, 2, 2, NoteType::Bomb, ANY_DIRECTION),
+            ]
+        );
+        let wall = &map.obstacles[0];
+        assert_eq!((wall.time, wall.duration, wall.column, wall.width), (2.0, 1.0, 1, 1));
+    }
+
+    #[test]
+    fn parses_v3() {
+        let map = parse_beatmap(
+            r#"{
+                "version": "3.3.0",
+                "colorNotes": [{ "b": 2, "x": 1, "y": 0, "c": 1, "d": 3 }],
+                "bombNotes": [{ "b": 1, "x": 3, "y": 5 }],
+                "obstacles": [{ "b": 1, "x": 0, "d": 1, "w": 2 }]
+            }"#,
+            60.0,
+        )
+        .unwrap();
+        assert_eq!(map.format, Some(BeatmapFormat::V3));
+        assert_eq!(
+            summary(&map),
+            [(1.0, 3, 2, NoteType::Bomb, ANY_DIRECTION), (2.0, 1, 0, NoteType::Right, 3)]
+        );
+        assert_eq!(map.obstacles[0].width, 2);
+    }
+
+    #[test]
+    fn parses_v4() {
+        let map = parse_beatmap(
+            r#"{
+                "version": "4.0.0",
+                "colorNotes": [{ "b": 1, "i": 1 }, { "b": 0.5, "i": 0 }],
+                "colorNotesData": [{ "x": 0, "y": 0, "c": 0, "d": 1 }, { "x": 3, "y": 1, "c": 1, "d": 2 }],
+                "bombNotes": [{ "b": 2, "i": 0 }],
+                "bombNotesData": [{ "x": 2, "y": 2 }],
+                "obstacles": [{ "b": 1, "i": 0 }],
+                "obstaclesData": [{ "d": 2, "x": 1, "w": 3 }]
+            }"#,
+            120.0,
+        )
+        .unwrap();
+        assert_eq!(map.format, Some(BeatmapFormat::V4));
+        assert_eq!(
+            summary(&map),
+            [
+                (0.25, 0, 0, NoteType::Left, 1),
+                (0.5, 3, 1, NoteType::Right, 2),
+                (1.0, 2, 2, NoteType::Bomb, ANY_DIRECTION),
+            ]
+        );
+        let wall = &map.obstacles[0];
+        assert_eq!((wall.time, wall.duration, wall.column, wall.width), (0.5, 1.0, 1, 3));
+    }
+
+    #[test]
+    fn obstacle_widths_are_clamped() {
+        let widths = |content: &str| -> Vec<u8> {
+            parse_beatmap(content, 120.0).unwrap().obstacles.iter().map(|o| o.width).collect()
+        };
+        assert_eq!(
+            widths(r#"{"_version":"2.0.0","_obstacles":[{"_time":0,"_width":300},{"_time":1,"_width":-5},{"_time":2,"_width":2}]}"#),
+            [255, 1, 2]
+        );
+        assert_eq!(widths(r#"{"version":"3.0.0","obstacles":[{"b":0,"w":256},{"b":1,"w":0}]}"#), [255, 1]);
+        assert_eq!(
+            widths(r#"{"version":"4.0.0","obstacles":[{"b":0,"i":0}],"obstaclesData":[{"w":1000}]}"#),
+            [255]
+        );
+    }
+
+    #[test]
+    fn v4_index_out_of_range_fails() {
+        let result = parse_beatmap(
+            r#"{ "version": "4.0.0", "colorNotes": [{ "b": 1, "i": 2 }], "colorNotesData": [{}] }"#,
+            120.0,
+        );
+        match result {
+            Err(AppError::InvalidBeatmap { message }) => assert!(message.contains("colorNotesData index 2")),
+            other => panic!("expected invalid_beatmap, got {:?}", other.map(|m| m.notes.len())),
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_beatmaps() {
+        for content in ["[", r#"{"notes": []}"#, r#"{"_notes": 1}"#] {
+            assert!(matches!(
+                parse_beatmap(content, 120.0),
+                Err(AppError::InvalidBeatmap { .. })
+            ));
+        }
+    }
+}
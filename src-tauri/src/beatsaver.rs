@@ -1,10 +1,22 @@
 use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
+use sha1::{Digest, Sha1};
 use std::collections::HashMap;
 use std::io::Read;
 use tauri::{AppHandle, Emitter, Manager};
 use tokio::sync::mpsc;
 
+use crate::cache;
+use crate::db::Database;
+use crate::error::{run_blocking, AppError};
+use crate::http::{retry_after_secs, HttpClient, HttpHandle};
+use crate::map_cache::{self, CacheStatus, MapManifest};
+use crate::beatmap;
+use crate::map_info::{self, InfoFormat};
+use crate::metadata_cache::{self, CachedEntry};
+use crate::settings::{BackendSettings, BeatSaverSettings, SettingsState, ZipLimitsSettings};
+
 // ---------------------------------------------------------------------------
 // BeatSaver API response types (matching their JSON exactly)
 // ---------------------------------------------------------------------------
@@ -160,6 +172,42 @@ pub struct BeatSaverMapData {
     pub cover_base64: Option<String>,
 }
 
+/// A file taken from a map ZIP, under its original (sanitized) name.
+#[derive(Debug, Clone)]
+pub struct ExtractedFile {
+    pub name: String,
+    pub bytes: Vec<u8>,
+}
+
+/// Raw contents of a map ZIP, as written to the download cache.
+#[derive(Debug, Clone)]
+pub struct ExtractedMap {
+    pub info_dat: String,
+    pub beatmaps: HashMap<String, String>,
+    pub audio: ExtractedFile,
+    pub cover: Option<ExtractedFile>,
+    /// Version hash computed from the files, when the layout allows it.
+    pub hash: Option<String>,
+    /// `hash` was compared with, and matched, the requested version hash.
+    pub hash_verified: bool,
+    /// Info.dat uses a layout whose version hash isn't computed (v4), so the
+    /// files could not be checked against the requested version.
+    pub hash_unverifiable: bool,
+    /// Problems found while resolving files through Info.dat.
+    pub warnings: Vec<String>,
+}
+
+impl ExtractedMap {
+    pub fn into_map_data(self) -> BeatSaverMapData {
+        BeatSaverMapData {
+            info_dat: self.info_dat,
+            beatmaps: self.beatmaps,
+            audio_base64: BASE64.encode(&self.audio.bytes),
+            cover_base64: self.cover.map(|cover| BASE64.encode(&cover.bytes)),
+        }
+    }
+}
+
 // ---------------------------------------------------------------------------
 // Queue types for background track downloads
 // ---------------------------------------------------------------------------
@@ -175,19 +223,13 @@ pub struct TrackFetchRequest {
 pub struct TrackFetchResult {
     pub map_id: String,
     pub status: String, // "success" | "already_cached" | "error"
-    pub error: Option<String>,
+    pub error: Option<AppError>,
 }
 
 pub struct TrackQueue {
     pub tx: mpsc::UnboundedSender<TrackFetchRequest>,
 }
 
-// ---------------------------------------------------------------------------
-// Constants
-// ---------------------------------------------------------------------------
-
-const API_BASE: &str = "https://api.beatsaver.com";
-
 // ---------------------------------------------------------------------------
 // URL param builder for search
 // ---------------------------------------------------------------------------
@@ -231,261 +273,612 @@ fn build_search_params(query: &str, page: i64, filters: &BeatSaverSearchFilters)
         .map(|(k, v)| format!("{}={}", urlencoding::encode(k), urlencoding::encode(v)))
         .collect();
 
-    format!("{}/search/text/{}?{}", API_BASE, page, qs.join("&"))
+    format!("/search/text/{}?{}", page, qs.join("&"))
 }
 
 // ---------------------------------------------------------------------------
-// Filesystem download cache helpers
+// Endpoint fallback helpers
 // ---------------------------------------------------------------------------
 
-fn downloads_dir(app_handle: &AppHandle) -> std::path::PathBuf {
-    let app_dir = app_handle
-        .path()
-        .app_data_dir()
-        .expect("Failed to get app data directory");
-    app_dir.join("downloads")
-}
+/// GET a JSON document from the configured API, falling back to mirrors when
+/// an endpoint is unreachable, throttling or returns a server error.
+async fn fetch_api_json<T: DeserializeOwned>(
+    client: &HttpHandle,
+    settings: &BeatSaverSettings,
+    path: &str,
+    context: &str,
+) -> Result<T, AppError> {
+    let mut last_error = AppError::network(format!("{} failed: no API endpoint configured", context));
+
+    for base in settings.api_bases() {
+        let url = format!("{}{}", base, path);
+        match client.fetch(&url).await {
+            Ok(response) if !response.status().is_success() => {
+                let status = response.status();
+                let error = AppError::from_status(status, retry_after_secs(&response), context);
+                if !status.is_server_error() && status != reqwest::StatusCode::TOO_MANY_REQUESTS {
+                    return Err(error);
+                }
+                last_error = error;
+            }
+            Ok(response) => {
+                return response.json().await.map_err(|e| {
+                    if e.is_decode() {
+                        AppError::invalid_response(format!("JSON parse error: {}", e))
+                    } else {
+                        AppError::network(format!("{} failed: {}", context, e))
+                    }
+                });
+            }
+            Err(e) => last_error = AppError::network(format!("{} failed: {}", context, e)),
+        }
+    }
 
-fn download_path(app_handle: &AppHandle, map_id: &str) -> std::path::PathBuf {
-    downloads_dir(app_handle).join(format!("{}.json", map_id))
+    Err(last_error)
 }
 
-fn has_downloaded_map(app_handle: &AppHandle, map_id: &str) -> bool {
-    download_path(app_handle, map_id).exists()
+/// Read a response body in chunks, refusing it as soon as it passes
+/// `max_bytes`, whether or not the server declared its length.
+async fn read_limited(mut response: reqwest::Response, max_bytes: u64) -> Result<Vec<u8>, AppError> {
+    let too_large = || AppError::zip_limit("maxTotalBytes", format!("Download is larger than {} bytes", max_bytes));
+    let declared = response.content_length().unwrap_or(0);
+    if declared > max_bytes {
+        return Err(too_large());
+    }
+
+    let mut body = Vec::with_capacity(declared as usize);
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .map_err(|e| AppError::network(format!("Download read failed: {}", e)))?
+    {
+        if (body.len() + chunk.len()) as u64 > max_bytes {
+            return Err(too_large());
+        }
+        body.extend_from_slice(&chunk);
+    }
+    Ok(body)
 }
 
-fn load_downloaded_map(app_handle: &AppHandle, map_id: &str) -> Result<Option<BeatSaverMapData>, String> {
-    let path = download_path(app_handle, map_id);
-    if !path.exists() {
-        return Ok(None);
+/// Download a map ZIP of at most `max_bytes`, trying the configured CDN, the
+/// original URL and then each mirror's CDN until one succeeds.
+async fn fetch_map_zip(
+    client: &HttpHandle,
+    settings: &BeatSaverSettings,
+    download_url: &str,
+    max_bytes: u64,
+) -> Result<Vec<u8>, AppError> {
+    let mut last_error = AppError::network("Download failed: no download URL");
+
+    for url in settings.download_urls(download_url) {
+        match client.fetch(&url).await {
+            Ok(response) if !response.status().is_success() => {
+                last_error =
+                    AppError::from_status(response.status(), retry_after_secs(&response), "Download");
+            }
+            Ok(response) => match read_limited(response, max_bytes).await {
+                Ok(bytes) => return Ok(bytes),
+                // Every mirror serves the same file, so it would be too large there too
+                Err(e @ AppError::ZipLimitExceeded { .. }) => return Err(e),
+                Err(e) => last_error = e,
+            },
+            Err(e) => last_error = AppError::network(format!("Download failed: {}", e)),
+        }
     }
-    let contents = std::fs::read_to_string(&path)
-        .map_err(|e| format!("Failed to read download cache: {}", e))?;
-    let data: BeatSaverMapData = serde_json::from_str(&contents)
-        .map_err(|e| format!("Failed to parse download cache: {}", e))?;
-    Ok(Some(data))
+
+    Err(last_error)
 }
 
-fn save_downloaded_map(app_handle: &AppHandle, map_id: &str, data: &BeatSaverMapData) -> Result<(), String> {
-    let dir = downloads_dir(app_handle);
-    std::fs::create_dir_all(&dir)
-        .map_err(|e| format!("Failed to create downloads directory: {}", e))?;
-    let path = dir.join(format!("{}.json", map_id));
-    let json = serde_json::to_string(data)
-        .map_err(|e| format!("Failed to serialize download data: {}", e))?;
-    std::fs::write(&path, json)
-        .map_err(|e| format!("Failed to write download cache: {}", e))?;
-    Ok(())
+/// Extract a downloaded ZIP and write it to the cache on the blocking pool:
+/// decompression, hashing and fsyncing every file would otherwise stall the
+/// async runtime.
+async fn extract_and_save(
+    app_handle: &AppHandle,
+    map_id: &str,
+    version_hash: Option<String>,
+    bytes: Vec<u8>,
+    limits: ZipLimitsSettings,
+) -> Result<MapManifest, AppError> {
+    let app_handle = app_handle.clone();
+    let map_id = map_id.to_string();
+    run_blocking(move || {
+        let map = extract_map_data(&bytes, version_hash.as_deref(), &limits)?;
+        let manifest = map_cache::save_map(&app_handle, &map_id, version_hash, &map)?;
+        cache::apply_quota(&app_handle, Some(&map_id));
+        Ok(manifest)
+    })
+    .await
 }
 
 // ---------------------------------------------------------------------------
 // Shared ZIP extraction
 // ---------------------------------------------------------------------------
 
-fn extract_map_data(bytes: &[u8]) -> Result<BeatSaverMapData, String> {
-    let cursor = std::io::Cursor::new(bytes);
-    let mut archive = zip::ZipArchive::new(cursor).map_err(|e| format!("ZIP error: {}", e))?;
+/// Entries past this size are also held to the compression ratio limit;
+/// small, highly repetitive JSON files legitimately compress very well.
+const RATIO_CHECK_MIN_BYTES: u64 = 1024 * 1024;
+const READ_CHUNK_BYTES: usize = 64 * 1024;
 
-    let mut info_dat = String::new();
-    let mut beatmaps: HashMap<String, String> = HashMap::new();
-    let mut audio_bytes: Option<Vec<u8>> = None;
-    let mut cover_bytes: Option<Vec<u8>> = None;
+/// Running totals checked against the ZIP limits while entries are read.
+struct ExtractBudget<'a> {
+    limits: &'a ZipLimitsSettings,
+    total_bytes: u64,
+}
+
+impl ExtractBudget<'_> {
+    /// Read one entry in chunks, failing as soon as a limit is crossed rather
+    /// than trusting the sizes declared in the ZIP headers.
+    fn read_entry(
+        &mut self,
+        file: &mut zip::read::ZipFile<'_>,
+        name: &str,
+        max_bytes: Option<(u64, &str)>,
+    ) -> Result<Vec<u8>, AppError> {
+        let compressed = file.compressed_size().max(1);
+        let mut buf = Vec::new();
+        let mut chunk = vec![0u8; READ_CHUNK_BYTES];
+        loop {
+            let n = file
+                .read(&mut chunk)
+                .map_err(|e| AppError::zip_invalid(format!("Read {}: {}", name, e)))?;
+            if n == 0 {
+                return Ok(buf);
+            }
+            buf.extend_from_slice(&chunk[..n]);
+            self.total_bytes += n as u64;
+            let size = buf.len() as u64;
+
+            if self.total_bytes > self.limits.max_total_bytes {
+                return Err(AppError::zip_limit(
+                    "maxTotalBytes",
+                    format!("ZIP expands to more than {} bytes", self.limits.max_total_bytes),
+                ));
+            }
+            if let Some((max, limit)) = max_bytes {
+                if size > max {
+                    return Err(AppError::zip_limit(
+                        limit,
+                        format!("{} is larger than {} bytes", name, max),
+                    ));
+                }
+            }
+            if size > RATIO_CHECK_MIN_BYTES && size / compressed > self.limits.max_compression_ratio {
+                return Err(AppError::zip_limit(
+                    "maxCompressionRatio",
+                    format!(
+                        "{} expands more than {}x its compressed size",
+                        name, self.limits.max_compression_ratio
+                    ),
+                ));
+            }
+        }
+    }
+}
 
+type MapArchive<'a> = zip::ZipArchive<std::io::Cursor<&'a [u8]>>;
+
+/// ZIP entries by lowercased file name, so Info.dat references resolve
+/// regardless of case or folder. The first entry with a given name wins.
+fn index_entries(archive: &mut MapArchive<'_>) -> Result<HashMap<String, (usize, String)>, AppError> {
+    let mut entries = HashMap::new();
     for i in 0..archive.len() {
-        let mut file = archive
-            .by_index(i)
-            .map_err(|e| format!("ZIP entry error: {}", e))?;
-        let name = file.name().to_lowercase();
-        let original_name = file.name().to_string();
-
-        if name == "info.dat" {
-            file.read_to_string(&mut info_dat)
-                .map_err(|e| format!("Read Info.dat: {}", e))?;
-        } else if name.ends_with(".dat") && name != "info.dat" {
-            let mut content = String::new();
-            file.read_to_string(&mut content)
-                .map_err(|e| format!("Read {}: {}", original_name, e))?;
-            beatmaps.insert(original_name, content);
-        } else if name.ends_with(".ogg") || name.ends_with(".egg") {
-            let mut buf = Vec::new();
-            file.read_to_end(&mut buf)
-                .map_err(|e| format!("Read audio: {}", e))?;
-            audio_bytes = Some(buf);
-        } else if name.ends_with(".jpg") || name.ends_with(".jpeg") || name.ends_with(".png") {
-            let mut buf = Vec::new();
-            file.read_to_end(&mut buf)
-                .map_err(|e| format!("Read cover: {}", e))?;
-            cover_bytes = Some(buf);
+        let file = archive
+            .by_index_raw(i)
+            .map_err(|e| AppError::zip_invalid(format!("ZIP entry error: {}", e)))?;
+        if file.is_dir() {
+            continue;
+        }
+        if let Some(name) = map_cache::safe_filename(file.name()) {
+            entries.entry(name.to_lowercase()).or_insert((i, name));
         }
     }
+    Ok(entries)
+}
+
+/// Read the entry Info.dat calls `filename`, or `None` if the ZIP lacks it.
+fn read_named(
+    archive: &mut MapArchive<'_>,
+    entries: &HashMap<String, (usize, String)>,
+    budget: &mut ExtractBudget<'_>,
+    filename: &str,
+    max_bytes: Option<(u64, &str)>,
+) -> Result<Option<ExtractedFile>, AppError> {
+    let Some((index, name)) = map_cache::safe_filename(filename).and_then(|n| entries.get(&n.to_lowercase())) else {
+        return Ok(None);
+    };
+    let mut file = archive
+        .by_index(*index)
+        .map_err(|e| AppError::zip_invalid(format!("ZIP entry error: {}", e)))?;
+    let bytes = budget.read_entry(&mut file, name, max_bytes)?;
+    Ok(Some(ExtractedFile {
+        name: name.clone(),
+        bytes,
+    }))
+}
+
+fn into_text(file: ExtractedFile) -> Result<(String, String), AppError> {
+    let text = String::from_utf8(file.bytes)
+        .map_err(|e| AppError::invalid_beatmap(format!("{} is not valid UTF-8: {}", file.name, e)))?;
+    Ok((file.name, text))
+}
+
+/// Extract a map ZIP within `limits`, reading exactly the files Info.dat
+/// references. Missing covers and difficulties and unreferenced entries are
+/// reported as warnings. When `expected_hash` is given and the version hash
+/// can be computed from the files, a mismatch is rejected.
+fn extract_map_data(
+    bytes: &[u8],
+    expected_hash: Option<&str>,
+    limits: &ZipLimitsSettings,
+) -> Result<ExtractedMap, AppError> {
+    let cursor = std::io::Cursor::new(bytes);
+    let mut archive = zip::ZipArchive::new(cursor).map_err(|e| AppError::zip_invalid(format!("ZIP error: {}", e)))?;
+    if archive.len() > limits.max_entries {
+        return Err(AppError::zip_limit(
+            "maxEntries",
+            format!("ZIP has {} entries (limit {})", archive.len(), limits.max_entries),
+        ));
+    }
 
-    let audio_base64 = match audio_bytes {
-        Some(bytes) => BASE64.encode(&bytes),
-        None => return Err("No audio file found in ZIP".to_string()),
+    let entries = index_entries(&mut archive)?;
+    let mut budget = ExtractBudget {
+        limits,
+        total_bytes: 0,
     };
+    let mut warnings = Vec::new();
+
+    let (info_name, info_dat) = read_named(&mut archive, &entries, &mut budget, "Info.dat", None)?
+        .map(into_text)
+        .transpose()?
+        .ok_or_else(|| AppError::MissingInfoDat {
+            message: "No Info.dat found in ZIP".to_string(),
+        })?;
+    let info = map_info::parse_info(&info_dat)?;
+    let files = info.referenced_files()?;
+
+    let max_audio = Some((limits.max_audio_bytes, "maxAudioBytes"));
+    let audio = read_named(&mut archive, &entries, &mut budget, &files.song, max_audio)?.ok_or_else(|| {
+        AppError::MissingAudio {
+            message: format!("Song file {} referenced by Info.dat is not in the ZIP", files.song),
+        }
+    })?;
+
+    let mut cover = None;
+    if let Some(filename) = &files.cover {
+        cover = read_named(&mut archive, &entries, &mut budget, filename, None)?;
+        if cover.is_none() {
+            warnings.push(format!("Cover image {} referenced by Info.dat is missing", filename));
+        }
+    }
+
+    let mut beatmaps: HashMap<String, String> = HashMap::new();
+    for filename in &files.beatmaps {
+        if find_beatmap(&beatmaps, filename).is_some() {
+            continue;
+        }
+        match read_named(&mut archive, &entries, &mut budget, filename, None)? {
+            Some(file) => {
+                let (name, content) = into_text(file)?;
+                if let Err(e) = beatmap::parse_beatmap(&content, info.bpm) {
+                    warnings.push(format!("Difficulty file {} could not be parsed: {}", name, e.message()));
+                }
+                beatmaps.insert(name, content);
+            }
+            None => warnings.push(format!("Difficulty file {} referenced by Info.dat is missing", filename)),
+        }
+    }
 
-    let cover_base64 = cover_bytes.map(|bytes| BASE64.encode(&bytes));
+    let mut used: Vec<&String> = beatmaps.keys().chain([&info_name, &audio.name]).collect();
+    used.extend(cover.as_ref().map(|c| &c.name));
+    let ignored: Vec<String> = files.ignored.iter().filter_map(|f| map_cache::safe_filename(f)).collect();
+    let mut unreferenced: Vec<&String> = entries
+        .values()
+        .map(|(_, name)| name)
+        .filter(|name| !used.contains(name) && !ignored.iter().any(|f| f.eq_ignore_ascii_case(name)))
+        .collect();
+    unreferenced.sort();
+    warnings.extend(
+        unreferenced
+            .into_iter()
+            .map(|name| format!("{} is not referenced by Info.dat and was skipped", name)),
+    );
 
-    if info_dat.is_empty() {
-        return Err("No Info.dat found in ZIP".to_string());
+    let hash = compute_version_hash(&info_dat, &beatmaps);
+    let hash_unverifiable = is_hash_unverifiable(&info_dat);
+    if hash_unverifiable {
+        warnings.push("Info.dat v4 maps can't be checked against the BeatSaver version hash".to_string());
+    }
+    let mut hash_verified = false;
+    if let (Some(expected), Some(actual)) = (expected_hash, hash.as_deref()) {
+        hash_verified = expected.eq_ignore_ascii_case(actual);
+        if !hash_verified {
+            return Err(AppError::HashMismatch {
+                expected: expected.to_string(),
+                actual: actual.to_string(),
+                message: format!("Downloaded map hashes to {} but version {} was requested", actual, expected),
+            });
+        }
     }
 
-    Ok(BeatSaverMapData {
+    Ok(ExtractedMap {
         info_dat,
         beatmaps,
-        audio_base64,
-        cover_base64,
+        audio,
+        cover,
+        hash,
+        hash_verified,
+        hash_unverifiable,
+        warnings,
+    })
+}
+
+// ---------------------------------------------------------------------------
+// Version hash
+// ---------------------------------------------------------------------------
+
+fn find_beatmap<'a>(beatmaps: &'a HashMap<String, String>, filename: &str) -> Option<&'a String> {
+    let name = map_cache::safe_filename(filename)?;
+    beatmaps.get(&name).or_else(|| {
+        beatmaps
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(&name))
+            .map(|(_, content)| content)
     })
 }
 
+/// Whether Info.dat uses a layout `compute_version_hash` doesn't cover (v4),
+/// so the map can't be checked against its version hash.
+pub fn is_hash_unverifiable(info_dat: &str) -> bool {
+    map_info::parse_info(info_dat).is_ok_and(|info| info.format != InfoFormat::V2)
+}
+
+/// BeatSaver's version hash: SHA-1 over Info.dat followed by each difficulty
+/// file in the order Info.dat lists them. Only the v2/v3 Info.dat layout is
+/// covered; `None` means the hash can't be computed (v4 layout, invalid JSON
+/// or a referenced file missing from the ZIP).
+pub fn compute_version_hash(info_dat: &str, beatmaps: &HashMap<String, String>) -> Option<String> {
+    let info = map_info::parse_info(info_dat).ok()?;
+    if info.format != InfoFormat::V2 {
+        return None;
+    }
+    let files = info.referenced_files().ok()?;
+
+    let mut hasher = Sha1::new();
+    hasher.update(info_dat.as_bytes());
+    for filename in &files.beatmaps {
+        hasher.update(find_beatmap(beatmaps, filename)?.as_bytes());
+    }
+    Some(hex::encode(hasher.finalize()))
+}
+
+// ---------------------------------------------------------------------------
+// Metadata cache helpers
+// ---------------------------------------------------------------------------
+
+/// Serve a search/browse response from the cache while it is fresh, otherwise
+/// fetch and store it. A stale copy is used if the network is unavailable.
+async fn fetch_query_cached(
+    client: &HttpHandle,
+    settings: &BackendSettings,
+    db: &Database,
+    path: &str,
+    cached: Option<CachedEntry<BeatSaverSearchResponse>>,
+    context: &str,
+) -> Result<BeatSaverSearchResponse, AppError> {
+    if let Some(entry) = &cached {
+        if entry.is_fresh(settings.metadata_cache.query_ttl_secs) {
+            return Ok(entry.value.clone());
+        }
+    }
+
+    match fetch_api_json::<BeatSaverSearchResponse>(client, &settings.beatsaver, path, context).await {
+        Ok(response) => {
+            metadata_cache::put_query(&*db.lock()?, path, &response)?;
+            Ok(response)
+        }
+        Err(e) if e.is_transient() => cached.map(|entry| entry.value).ok_or(e),
+        Err(e) => Err(e),
+    }
+}
+
 // ---------------------------------------------------------------------------
 // Tauri commands
 // ---------------------------------------------------------------------------
 
 /// Legacy direct download command (kept for backward compatibility).
 #[tauri::command]
-pub async fn beatsaver_download(url: String) -> Result<BeatSaverMapData, String> {
-    let bytes = tauri::async_runtime::spawn_blocking(move || {
-        let response =
-            reqwest::blocking::get(&url).map_err(|e| format!("Download failed: {}", e))?;
-        if !response.status().is_success() {
-            return Err(format!("HTTP {}", response.status()));
-        }
-        response
-            .bytes()
-            .map_err(|e| format!("Read failed: {}", e))
-    })
-    .await
-    .map_err(|e| e.to_string())?
-    .map_err(|e: String| e)?;
+pub async fn beatsaver_download(
+    url: String,
+    settings: tauri::State<'_, SettingsState>,
+    http: tauri::State<'_, HttpClient>,
+) -> Result<BeatSaverMapData, AppError> {
+    let limits = settings.get().zip_limits;
+    let response = http
+        .handle()
+        .fetch(&url)
+        .await
+        .map_err(|e| AppError::network(format!("Download failed: {}", e)))?;
+    if !response.status().is_success() {
+        return Err(AppError::from_status(
+            response.status(),
+            retry_after_secs(&response),
+            "Download",
+        ));
+    }
+    let bytes = read_limited(response, limits.max_total_bytes).await?;
 
-    extract_map_data(bytes.as_ref())
+    let expected_hash = map_cache::version_hash_from_url(&url);
+    run_blocking(move || Ok(extract_map_data(&bytes, expected_hash.as_deref(), &limits)?.into_map_data())).await
 }
 
-/// Search BeatSaver maps.
+/// Search BeatSaver maps. Fresh cached responses are served without a
+/// request; when offline, falls back to a local search of cached maps.
 #[tauri::command]
 pub async fn beatsaver_search(
     query: String,
     page: i64,
     filters: BeatSaverSearchFilters,
-) -> Result<BeatSaverSearchResponse, String> {
-    let url = build_search_params(&query, page, &filters);
-
-    let client = reqwest::Client::new();
-    let response = client
-        .get(&url)
-        .send()
-        .await
-        .map_err(|e| format!("Search failed: {}", e))?;
-
-    if !response.status().is_success() {
-        return Err(format!("Search failed: HTTP {}", response.status()));
+    settings: tauri::State<'_, SettingsState>,
+    http: tauri::State<'_, HttpClient>,
+    db: tauri::State<'_, Database>,
+) -> Result<BeatSaverSearchResponse, AppError> {
+    let path = build_search_params(&query, page, &filters);
+    let settings = settings.get();
+    let cached = metadata_cache::get_query(&*db.lock()?, &path)?;
+
+    if settings.offline {
+        if let Some(entry) = cached {
+            return Ok(entry.value);
+        }
+        // Filters need the server; offline search only matches names and authors
+        let docs = if page == 0 {
+            metadata_cache::search_maps(&*db.lock()?, &query, filters.page_size)?
+        } else {
+            Vec::new()
+        };
+        return Ok(BeatSaverSearchResponse { docs, info: None });
     }
 
-    response
-        .json()
-        .await
-        .map_err(|e| format!("JSON parse error: {}", e))
+    fetch_query_cached(&http.handle(), &settings, &db, &path, cached, "Search").await
 }
 
-/// Get latest/browse maps by category.
+/// Get latest/browse maps by category. When offline and the category was
+/// never fetched, lists downloaded maps whose metadata is cached.
 #[tauri::command]
 pub async fn beatsaver_browse(
     sort: String,
     page_size: i64,
-) -> Result<BeatSaverSearchResponse, String> {
-    let url = format!(
-        "{}/maps/latest?sort={}&automapper=false&pageSize={}",
-        API_BASE,
+    settings: tauri::State<'_, SettingsState>,
+    http: tauri::State<'_, HttpClient>,
+    db: tauri::State<'_, Database>,
+    app_handle: AppHandle,
+) -> Result<BeatSaverSearchResponse, AppError> {
+    let path = format!(
+        "/maps/latest?sort={}&automapper=false&pageSize={}",
         urlencoding::encode(&sort),
         page_size
     );
+    let settings = settings.get();
+    let cached = metadata_cache::get_query(&*db.lock()?, &path)?;
 
-    let client = reqwest::Client::new();
-    let response = client
-        .get(&url)
-        .send()
-        .await
-        .map_err(|e| format!("Browse failed: {}", e))?;
-
-    if !response.status().is_success() {
-        return Err(format!("Browse failed: HTTP {}", response.status()));
+    if settings.offline {
+        if let Some(entry) = cached {
+            return Ok(entry.value);
+        }
+        let ids = map_cache::downloaded_map_ids(&app_handle);
+        let docs = metadata_cache::maps_by_ids(&*db.lock()?, &ids)?;
+        return Ok(BeatSaverSearchResponse { docs, info: None });
     }
 
-    response
-        .json()
-        .await
-        .map_err(|e| format!("JSON parse error: {}", e))
+    fetch_query_cached(&http.handle(), &settings, &db, &path, cached, "Browse").await
 }
 
-/// Get a single map by ID.
+/// Get a single map by ID, from the metadata cache when fresh or offline.
 #[tauri::command]
 pub async fn beatsaver_get_map(
     id: String,
-) -> Result<BeatSaverMap, String> {
-    let url = format!("{}/maps/id/{}", API_BASE, urlencoding::encode(&id));
-
-    let client = reqwest::Client::new();
-    let response = client
-        .get(&url)
-        .send()
-        .await
-        .map_err(|e| format!("Map fetch failed: {}", e))?;
+    settings: tauri::State<'_, SettingsState>,
+    http: tauri::State<'_, HttpClient>,
+    db: tauri::State<'_, Database>,
+) -> Result<BeatSaverMap, AppError> {
+    let path = format!("/maps/id/{}", urlencoding::encode(&id));
+    let settings = settings.get();
+    let cached = metadata_cache::get_map(&*db.lock()?, &id)?;
+
+    match cached {
+        Some(entry) if settings.offline || entry.is_fresh(settings.metadata_cache.map_ttl_secs) => {
+            return Ok(entry.value)
+        }
+        None if settings.offline => {
+            return Err(AppError::offline(format!("Map {} is not available offline", id)))
+        }
+        _ => {}
+    }
 
-    if !response.status().is_success() {
-        return Err(format!("Map fetch failed: HTTP {}", response.status()));
+    match fetch_api_json::<BeatSaverMap>(&http.handle(), &settings.beatsaver, &path, "Map fetch").await {
+        Ok(map) => {
+            metadata_cache::put_map(&*db.lock()?, &map)?;
+            Ok(map)
+        }
+        Err(e) if e.is_transient() => cached.map(|entry| entry.value).ok_or(e),
+        Err(e) => Err(e),
     }
+}
 
-    response
-        .json()
-        .await
-        .map_err(|e| format!("JSON parse error: {}", e))
+/// Latest version hash of a map according to the metadata cache.
+fn latest_cached_version_hash(db: &Database, map_id: &str) -> Result<Option<String>, AppError> {
+    Ok(metadata_cache::get_map(&*db.lock()?, map_id)?
+        .and_then(|entry| entry.value.versions.into_iter().next())
+        .map(|version| version.hash))
 }
 
-/// Check if a map's data has been downloaded and cached on disk.
+/// Check whether a map is downloaded and, if so, whether it is the latest
+/// version. `version_hash` defaults to `versions[0].hash` from the metadata
+/// cache; without either, any cached version counts as current.
 #[tauri::command]
 pub fn beatsaver_has_download(
     map_id: String,
+    version_hash: Option<String>,
+    db: tauri::State<'_, Database>,
     app_handle: AppHandle,
-) -> Result<bool, String> {
-    Ok(has_downloaded_map(&app_handle, &map_id))
+) -> Result<CacheStatus, AppError> {
+    let version_hash = match version_hash {
+        Some(hash) => Some(hash),
+        None => latest_cached_version_hash(&db, &map_id)?,
+    };
+    map_cache::cache_status(&app_handle, &map_id, version_hash.as_deref())
 }
 
-/// Download a track synchronously. Checks filesystem cache first.
+/// Download a track synchronously and return its cache manifest. Checks
+/// filesystem cache first; the files themselves are served over `moontapper://`.
+/// A cached copy of an older version than `download_url` points at is
+/// replaced, or served flagged `outdated` when offline or BeatSaver can't be
+/// reached. Scores are keyed by map id, so they stay linked across versions.
 #[tauri::command]
 pub async fn beatsaver_download_track(
     map_id: String,
     download_url: String,
+    settings: tauri::State<'_, SettingsState>,
+    http: tauri::State<'_, HttpClient>,
+    db: tauri::State<'_, Database>,
     app_handle: AppHandle,
-) -> Result<BeatSaverMapData, String> {
+) -> Result<MapManifest, AppError> {
     // Check filesystem cache first
-    if let Some(cached) = load_downloaded_map(&app_handle, &map_id)? {
-        return Ok(cached);
-    }
+    let version_hash = map_cache::version_hash_from_url(&download_url);
+    let cached = map_cache::read_manifest(&app_handle, &map_id)?;
+    let settings = settings.get();
+    let manifest = match cached {
+        Some(manifest) if manifest.matches_version(version_hash.as_deref()) => manifest,
+        Some(manifest) if settings.offline => outdated(manifest),
+        None if settings.offline => {
+            return Err(AppError::offline(format!("Map {} is not downloaded", map_id)))
+        }
+        cached => {
+            // Download and extract, falling back to an older cached version
+            // when BeatSaver can't be reached
+            let client = http.handle();
+            let max_bytes = settings.zip_limits.max_total_bytes;
+            match fetch_map_zip(&client, &settings.beatsaver, &download_url, max_bytes).await {
+                Ok(bytes) => {
+                    extract_and_save(&app_handle, &map_id, version_hash, bytes, settings.zip_limits).await?
+                }
+                Err(e) if e.is_transient() => cached.map(outdated).ok_or(e)?,
+                Err(e) => return Err(e),
+            }
+        }
+    };
 
-    // Download and extract
-    let client = reqwest::Client::new();
-    let response = client
-        .get(&download_url)
-        .send()
-        .await
-        .map_err(|e| format!("Download failed: {}", e))?;
+    // Loading a track for play keeps it at the back of the eviction queue
+    cache::record_play(&*db.lock()?, &map_id)?;
+    Ok(manifest)
+}
 
-    if !response.status().is_success() {
-        return Err(format!("HTTP {}", response.status()));
+/// A cached manifest served in place of the version that was asked for.
+fn outdated(manifest: MapManifest) -> MapManifest {
+    MapManifest {
+        outdated: true,
+        ..manifest
     }
-
-    let bytes = response
-        .bytes()
-        .await
-        .map_err(|e| format!("Read failed: {}", e))?;
-
-    let data = extract_map_data(bytes.as_ref())?;
-
-    // Cache to filesystem
-    let _ = save_downloaded_map(&app_handle, &map_id, &data);
-
-    Ok(data)
 }
 
 /// Enqueue a track for background download+extract.
@@ -494,14 +887,14 @@ pub fn beatsaver_fetch_track(
     map_id: String,
     download_url: String,
     queue: tauri::State<'_, TrackQueue>,
-) -> Result<String, String> {
+) -> Result<String, AppError> {
     queue
         .tx
         .send(TrackFetchRequest {
             map_id: map_id.clone(),
             download_url,
         })
-        .map_err(|e| format!("Failed to enqueue track fetch: {}", e))?;
+        .map_err(|e| AppError::internal(format!("Failed to enqueue track fetch: {}", e)))?;
     Ok(map_id)
 }
 
@@ -514,9 +907,8 @@ pub fn spawn_track_worker(
     mut rx: mpsc::UnboundedReceiver<TrackFetchRequest>,
 ) {
     tauri::async_runtime::spawn(async move {
-        let client = reqwest::Client::new();
-
         while let Some(request) = rx.recv().await {
+            let client = app_handle.state::<HttpClient>().handle();
             let result = process_track_request(&app_handle, &client, &request).await;
             let _ = app_handle.emit("beatsaver:track-ready", &result);
         }
@@ -525,11 +917,14 @@ pub fn spawn_track_worker(
 
 async fn process_track_request(
     app_handle: &AppHandle,
-    client: &reqwest::Client,
+    client: &HttpHandle,
     request: &TrackFetchRequest,
 ) -> TrackFetchResult {
     // Dedup protection: re-check cache before downloading
-    if has_downloaded_map(app_handle, &request.map_id) {
+    let version_hash = map_cache::version_hash_from_url(&request.download_url);
+    let cached = map_cache::cache_status(app_handle, &request.map_id, version_hash.as_deref());
+    // v4 maps stay unverifiable however often they're fetched again
+    if cached.is_ok_and(|status| matches!(status, CacheStatus::Cached | CacheStatus::Unverifiable)) {
         return TrackFetchResult {
             map_id: request.map_id.clone(),
             status: "already_cached".to_string(),
@@ -537,46 +932,36 @@ async fn process_track_request(
         };
     }
 
+    let settings = app_handle.state::<SettingsState>().get();
+    if settings.offline {
+        return TrackFetchResult {
+            map_id: request.map_id.clone(),
+            status: "error".to_string(),
+            error: Some(AppError::offline("Downloads are disabled in offline mode")),
+        };
+    }
+
     // Download the ZIP
-    let bytes = match client.get(&request.download_url).send().await {
-        Ok(resp) => {
-            if !resp.status().is_success() {
-                return TrackFetchResult {
-                    map_id: request.map_id.clone(),
-                    status: "error".to_string(),
-                    error: Some(format!("HTTP {}", resp.status())),
-                };
-            }
-            match resp.bytes().await {
-                Ok(b) => b,
-                Err(e) => {
-                    return TrackFetchResult {
-                        map_id: request.map_id.clone(),
-                        status: "error".to_string(),
-                        error: Some(format!("Download read failed: {}", e)),
-                    }
-                }
-            }
-        }
+    let max_bytes = settings.zip_limits.max_total_bytes;
+    let bytes = match fetch_map_zip(client, &settings.beatsaver, &request.download_url, max_bytes).await {
+        Ok(b) => b,
         Err(e) => {
             return TrackFetchResult {
                 map_id: request.map_id.clone(),
                 status: "error".to_string(),
-                error: Some(format!("Download failed: {}", e)),
+                error: Some(e),
             }
         }
     };
 
     // Extract ZIP and cache to filesystem
-    match extract_map_data(bytes.as_ref()) {
-        Ok(data) => {
-            let _ = save_downloaded_map(app_handle, &request.map_id, &data);
-            TrackFetchResult {
-                map_id: request.map_id.clone(),
-                status: "success".to_string(),
-                error: None,
-            }
-        }
+    let saved = extract_and_save(app_handle, &request.map_id, version_hash, bytes, settings.zip_limits).await;
+    match saved {
+        Ok(_) => TrackFetchResult {
+            map_id: request.map_id.clone(),
+            status: "success".to_string(),
+            error: None,
+        },
         Err(e) => TrackFetchResult {
             map_id: request.map_id.clone(),
             status: "error".to_string(),
@@ -584,3 +969,183 @@ async fn process_track_request(
         },
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use zip::write::SimpleFileOptions;
+    use zip::CompressionMethod;
+
+    const INFO_V2: &str = r#"{
+        "_version": "2.1.0",
+        "_songName": "Song",
+        "_beatsPerMinute": 120,
+        "_songFilename": "song.egg",
+        "_coverImageFilename": "cover.jpg",
+        "_difficultyBeatmapSets": [{
+            "_beatmapCharacteristicName": "Standard",
+            "_difficultyBeatmaps": [
+                { "_difficulty": "Expert", "_beatmapFilename": "Expert.dat" }
+            ]
+        }]
+    }"#;
+    const BEATMAP_V2: &str = r#"{"_version":"2.0.0","_notes":[{"_time":1,"_lineIndex":0,"_lineLayer":0,"_type":0,"_cutDirection":1}]}"#;
+
+    fn zip_of(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut writer = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+        for (name, bytes) in files {
+            writer.start_file(*name, options).unwrap();
+            writer.write_all(bytes).unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    fn limits() -> ZipLimitsSettings {
+        ZipLimitsSettings {
+            max_entries: 16,
+            max_total_bytes: 16 * 1024 * 1024,
+            max_compression_ratio: 100,
+            max_audio_bytes: 8 * 1024 * 1024,
+        }
+    }
+
+    fn limit_hit(result: Result<ExtractedMap, AppError>) -> String {
+        match result {
+            Err(AppError::ZipLimitExceeded { limit, .. }) => limit,
+            other => panic!("expected a limit error, got {:?}", other.map(|m| m.warnings)),
+        }
+    }
+
+    fn v2_hash(beatmap: &str) -> String {
+        let mut hasher = Sha1::new();
+        hasher.update(INFO_V2.as_bytes());
+        hasher.update(beatmap.as_bytes());
+        hex::encode(hasher.finalize())
+    }
+
+    #[test]
+    fn extracts_files_referenced_by_info_dat() {
+        let zip = zip_of(&[
+            ("Info.dat", INFO_V2.as_bytes()),
+            // References resolve regardless of case and folder
+            ("nested/SONG.egg", b"audio"),
+            ("cover.jpg", b"cover"),
+            ("Expert.dat", BEATMAP_V2.as_bytes()),
+            ("readme.txt", b"hello"),
+        ]);
+        let map = extract_map_data(&zip, None, &limits()).unwrap();
+        assert_eq!(map.audio.name, "SONG.egg");
+        assert_eq!(map.audio.bytes, b"audio");
+        assert_eq!(map.cover.unwrap().bytes, b"cover");
+        assert_eq!(map.beatmaps.keys().collect::<Vec<_>>(), ["Expert.dat"]);
+        assert_eq!(map.warnings, ["readme.txt is not referenced by Info.dat and was skipped"]);
+        assert_eq!(map.hash.as_deref(), Some(v2_hash(BEATMAP_V2).as_str()));
+        assert!(!map.hash_verified);
+        assert!(!map.hash_unverifiable);
+    }
+
+    #[test]
+    fn missing_optional_files_are_warnings() {
+        let zip = zip_of(&[("Info.dat", INFO_V2.as_bytes()), ("song.egg", b"audio")]);
+        let map = extract_map_data(&zip, None, &limits()).unwrap();
+        assert!(map.cover.is_none());
+        assert!(map.beatmaps.is_empty());
+        assert_eq!(map.warnings.len(), 2);
+        // A referenced difficulty is missing, so the hash can't be computed
+        assert!(map.hash.is_none());
+    }
+
+    #[test]
+    fn missing_info_dat_or_song_fails() {
+        let no_info = zip_of(&[("song.egg", b"audio")]);
+        assert!(matches!(
+            extract_map_data(&no_info, None, &limits()),
+            Err(AppError::MissingInfoDat { .. })
+        ));
+        let no_song = zip_of(&[("Info.dat", INFO_V2.as_bytes())]);
+        assert!(matches!(
+            extract_map_data(&no_song, None, &limits()),
+            Err(AppError::MissingAudio { .. })
+        ));
+    }
+
+    #[test]
+    fn expected_hash_is_checked() {
+        let zip = zip_of(&[
+            ("Info.dat", INFO_V2.as_bytes()),
+            ("song.egg", b"audio"),
+            ("Expert.dat", BEATMAP_V2.as_bytes()),
+        ]);
+        let expected = v2_hash(BEATMAP_V2).to_uppercase();
+        let map = extract_map_data(&zip, Some(&expected), &limits()).unwrap();
+        assert!(map.hash_verified);
+
+        let wrong = "0".repeat(40);
+        assert!(matches!(
+            extract_map_data(&zip, Some(&wrong), &limits()),
+            Err(AppError::HashMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn v4_maps_are_unverifiable() {
+        let info = r#"{
+            "version": "4.0.0",
+            "song": { "title": "Song" },
+            "audio": { "songFilename": "song.ogg", "bpm": 100 },
+            "difficultyBeatmaps": []
+        }"#;
+        let zip = zip_of(&[("Info.dat", info.as_bytes()), ("song.ogg", b"audio")]);
+        let map = extract_map_data(&zip, Some(&"0".repeat(40)), &limits()).unwrap();
+        assert!(map.hash.is_none());
+        assert!(!map.hash_verified);
+        assert!(map.hash_unverifiable);
+    }
+
+    #[test]
+    fn entry_count_limit() {
+        let zip = zip_of(&[("Info.dat", INFO_V2.as_bytes()), ("song.egg", b"audio"), ("a", b""), ("b", b"")]);
+        let limits = ZipLimitsSettings {
+            max_entries: 3,
+            ..limits()
+        };
+        assert_eq!(limit_hit(extract_map_data(&zip, None, &limits)), "maxEntries");
+    }
+
+    #[test]
+    fn total_size_limit() {
+        let song = vec![7u8; 4096];
+        let zip = zip_of(&[("Info.dat", INFO_V2.as_bytes()), ("song.egg", &song)]);
+        let limits = ZipLimitsSettings {
+            max_total_bytes: 2048,
+            ..limits()
+        };
+        assert_eq!(limit_hit(extract_map_data(&zip, None, &limits)), "maxTotalBytes");
+    }
+
+    #[test]
+    fn audio_size_limit() {
+        let song = vec![7u8; 4096];
+        let zip = zip_of(&[("Info.dat", INFO_V2.as_bytes()), ("song.egg", &song)]);
+        let limits = ZipLimitsSettings {
+            max_audio_bytes: 1024,
+            ..limits()
+        };
+        assert_eq!(limit_hit(extract_map_data(&zip, None, &limits)), "maxAudioBytes");
+    }
+
+    #[test]
+    fn compression_ratio_limit_applies_past_the_threshold() {
+        // Zeros deflate far beyond 100x
+        let bomb = vec![0u8; 4 * 1024 * 1024];
+        let zip = zip_of(&[("Info.dat", INFO_V2.as_bytes()), ("song.egg", &bomb)]);
+        assert_eq!(limit_hit(extract_map_data(&zip, None, &limits())), "maxCompressionRatio");
+
+        // Small files may compress as well as they like
+        let small = vec![0u8; 512 * 1024];
+        let zip = zip_of(&[("Info.dat", INFO_V2.as_bytes()), ("song.egg", &small)]);
+        assert!(extract_map_data(&zip, None, &limits()).is_ok());
+    }
+}
@@ -0,0 +1,345 @@
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use tauri::{AppHandle, Manager};
+
+use crate::db::Database;
+use crate::error::{run_blocking, AppError};
+use crate::map_cache::{self, MapManifest};
+use crate::metadata_cache::now_secs;
+use crate::settings::SettingsState;
+
+// ---------------------------------------------------------------------------
+// Download cache quota, usage tracking and statistics
+// ---------------------------------------------------------------------------
+
+/// Number of entries reported in `CacheStats::largest`.
+const LARGEST_ENTRIES: usize = 10;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheEntrySize {
+    pub map_id: String,
+    pub size_bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheStats {
+    pub total_bytes: u64,
+    pub map_count: usize,
+    /// Configured quota in bytes; 0 means unlimited.
+    pub quota_bytes: u64,
+    /// Maps protected from eviction because they are in a playlist.
+    pub pinned_count: usize,
+    pub largest: Vec<CacheEntrySize>,
+}
+
+/// One downloaded map as listed by `cache_list`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CachedMapInfo {
+    pub map_id: String,
+    pub size_bytes: u64,
+    pub downloaded_at: i64,
+    pub last_played_at: Option<i64>,
+    pub version_hash: Option<String>,
+    pub pinned: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CorruptCacheEntry {
+    pub map_id: String,
+    pub error: AppError,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheVerifyResult {
+    pub checked: usize,
+    pub corrupt: Vec<CorruptCacheEntry>,
+    /// Intact maps whose files can't be checked against their version hash.
+    pub unverifiable: Vec<String>,
+}
+
+/// Mark a map as just played, for least-recently-played eviction.
+pub fn record_play(conn: &Connection, map_id: &str) -> Result<(), AppError> {
+    conn.execute(
+        "INSERT OR REPLACE INTO map_usage (map_id, last_played_at) VALUES (?1, ?2)",
+        params![map_id, now_secs()],
+    )?;
+    Ok(())
+}
+
+fn last_played(conn: &Connection) -> Result<HashMap<String, i64>, AppError> {
+    let mut stmt = conn.prepare("SELECT map_id, last_played_at FROM map_usage")?;
+    let rows = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<rusqlite::Result<HashMap<_, _>>>()?;
+    Ok(rows)
+}
+
+/// Maps in any playlist (favorites included) are never evicted.
+fn pinned_map_ids(conn: &Connection) -> Result<HashSet<String>, AppError> {
+    let mut stmt = conn.prepare("SELECT DISTINCT track_id FROM playlist_tracks")?;
+    let rows = stmt
+        .query_map([], |row| row.get(0))?
+        .collect::<rusqlite::Result<HashSet<_>>>()?;
+    Ok(rows)
+}
+
+/// Every complete entry with its size on disk, derived files included.
+fn sized_manifests(app_handle: &AppHandle) -> Vec<(MapManifest, u64)> {
+    map_cache::list_manifests(app_handle)
+        .into_iter()
+        .map(|m| {
+            let size = map_cache::disk_size(app_handle, &m);
+            (m, size)
+        })
+        .collect()
+}
+
+/// Ids to evict, least recently played first, until the entries fit in
+/// `max_bytes`. Pinned maps and `keep` are never chosen; maps never played
+/// count from their download time.
+fn eviction_order<'a>(
+    entries: &'a [(MapManifest, u64)],
+    max_bytes: u64,
+    pinned: &HashSet<String>,
+    played: &HashMap<String, i64>,
+    keep: Option<&str>,
+) -> Vec<&'a str> {
+    let mut total: u64 = entries.iter().map(|(_, size)| size).sum();
+    let last_used = |m: &MapManifest| played.get(&m.map_id).copied().unwrap_or(m.downloaded_at);
+
+    let mut candidates: Vec<&(MapManifest, u64)> = entries
+        .iter()
+        .filter(|(m, _)| Some(m.map_id.as_str()) != keep && !pinned.contains(&m.map_id))
+        .collect();
+    candidates.sort_by_key(|(m, _)| last_used(m));
+
+    let mut victims = Vec::new();
+    for (manifest, size) in candidates {
+        if total <= max_bytes {
+            break;
+        }
+        total = total.saturating_sub(*size);
+        victims.push(manifest.map_id.as_str());
+    }
+    victims
+}
+
+/// Evict least recently played, unpinned maps until the cache fits in
+/// `max_bytes`. `keep` is a map that was just downloaded and must stay.
+/// Returns the ids removed.
+pub fn enforce_quota(
+    app_handle: &AppHandle,
+    conn: &Connection,
+    max_bytes: u64,
+    keep: Option<&str>,
+) -> Result<Vec<String>, AppError> {
+    if max_bytes == 0 {
+        return Ok(Vec::new());
+    }
+    let entries = sized_manifests(app_handle);
+    let total: u64 = entries.iter().map(|(_, size)| size).sum();
+    if total <= max_bytes {
+        return Ok(Vec::new());
+    }
+
+    let pinned = pinned_map_ids(conn)?;
+    let played = last_played(conn)?;
+    let mut evicted = Vec::new();
+    for map_id in eviction_order(&entries, max_bytes, &pinned, &played, keep) {
+        map_cache::delete_map(app_handle, map_id)?;
+        conn.execute("DELETE FROM map_usage WHERE map_id = ?1", params![map_id])?;
+        evicted.push(map_id.to_string());
+    }
+    Ok(evicted)
+}
+
+/// Apply the configured quota, e.g. after a download (`keep` being the new
+/// map) or a settings change. Failures here never fail the caller.
+pub fn apply_quota(app_handle: &AppHandle, keep: Option<&str>) {
+    let max_bytes = app_handle.state::<SettingsState>().get().download_cache.max_bytes;
+    let db = app_handle.state::<Database>();
+    if let Ok(conn) = db.lock() {
+        let _ = enforce_quota(app_handle, &conn, max_bytes, keep);
+    };
+}
+
+// ---------------------------------------------------------------------------
+// Tauri commands
+// ---------------------------------------------------------------------------
+
+/// Total size, map count and largest entries of the download cache.
+#[tauri::command]
+pub fn cache_stats(
+    settings: tauri::State<'_, SettingsState>,
+    db: tauri::State<'_, Database>,
+    app_handle: AppHandle,
+) -> Result<CacheStats, AppError> {
+    let entries = sized_manifests(&app_handle);
+    let pinned = pinned_map_ids(&*db.lock()?)?;
+
+    let mut largest: Vec<CacheEntrySize> = entries
+        .iter()
+        .map(|(m, size)| CacheEntrySize {
+            map_id: m.map_id.clone(),
+            size_bytes: *size,
+        })
+        .collect();
+    largest.sort_by_key(|entry| std::cmp::Reverse(entry.size_bytes));
+    largest.truncate(LARGEST_ENTRIES);
+
+    Ok(CacheStats {
+        total_bytes: entries.iter().map(|(_, size)| size).sum(),
+        map_count: entries.len(),
+        quota_bytes: settings.get().download_cache.max_bytes,
+        pinned_count: entries.iter().filter(|(m, _)| pinned.contains(&m.map_id)).count(),
+        largest,
+    })
+}
+
+/// Every downloaded map, most recently downloaded first.
+#[tauri::command]
+pub fn cache_list(
+    db: tauri::State<'_, Database>,
+    app_handle: AppHandle,
+) -> Result<Vec<CachedMapInfo>, AppError> {
+    let (pinned, played) = {
+        let conn = db.lock()?;
+        (pinned_map_ids(&conn)?, last_played(&conn)?)
+    };
+
+    let mut maps: Vec<CachedMapInfo> = sized_manifests(&app_handle)
+        .into_iter()
+        .map(|(m, size_bytes)| CachedMapInfo {
+            pinned: pinned.contains(&m.map_id),
+            last_played_at: played.get(&m.map_id).copied(),
+            map_id: m.map_id,
+            size_bytes,
+            downloaded_at: m.downloaded_at,
+            version_hash: m.version_hash,
+        })
+        .collect();
+    maps.sort_by_key(|m| std::cmp::Reverse(m.downloaded_at));
+    Ok(maps)
+}
+
+/// Remove one downloaded map. Playlist entries and scores are kept.
+#[tauri::command]
+pub fn cache_delete(
+    map_id: String,
+    db: tauri::State<'_, Database>,
+    app_handle: AppHandle,
+) -> Result<(), AppError> {
+    map_cache::delete_map(&app_handle, &map_id)?;
+    db.lock()?.execute("DELETE FROM map_usage WHERE map_id = ?1", params![map_id])?;
+    Ok(())
+}
+
+/// Remove every downloaded map, pinned ones included. Returns the number of
+/// entries removed.
+#[tauri::command]
+pub fn cache_purge(
+    db: tauri::State<'_, Database>,
+    app_handle: AppHandle,
+) -> Result<usize, AppError> {
+    let removed = map_cache::delete_all(&app_handle)?;
+    db.lock()?.execute("DELETE FROM map_usage", [])?;
+    Ok(removed)
+}
+
+/// Re-open every cache entry and report the ones that are incomplete or
+/// unreadable. Nothing is deleted.
+#[tauri::command]
+pub async fn cache_verify(app_handle: AppHandle) -> Result<CacheVerifyResult, AppError> {
+    run_blocking(move || {
+        let ids = map_cache::entry_dir_ids(&app_handle);
+        let mut corrupt = Vec::new();
+        let mut unverifiable = Vec::new();
+        for id in &ids {
+            match map_cache::verify_map(&app_handle, id) {
+                Ok(manifest) if manifest.hash_unverifiable => unverifiable.push(id.clone()),
+                Ok(_) => {}
+                Err(error) => corrupt.push(CorruptCacheEntry {
+                    map_id: id.clone(),
+                    error,
+                }),
+            }
+        }
+        Ok(CacheVerifyResult {
+            checked: ids.len(),
+            corrupt,
+            unverifiable,
+        })
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::migrations::apply_migrations;
+
+    fn entry(map_id: &str, downloaded_at: i64, size: u64) -> (MapManifest, u64) {
+        let manifest = MapManifest {
+            format: 1,
+            map_id: map_id.to_string(),
+            audio_filename: "song.egg".into(),
+            cover_filename: None,
+            beatmap_filenames: Vec::new(),
+            downloaded_at,
+            size_bytes: size,
+            version_hash: None,
+            hash_verified: false,
+            hash_unverifiable: false,
+            warnings: Vec::new(),
+            outdated: false,
+        };
+        (manifest, size)
+    }
+
+    #[test]
+    fn evicts_least_recently_played_first() {
+        let entries = [entry("a", 100, 10), entry("b", 200, 10), entry("c", 300, 10), entry("d", 400, 10)];
+        // "a" was played after everything else was downloaded; "c" never played
+        let played = HashMap::from([("a".to_string(), 500), ("b".to_string(), 250)]);
+        let victims = eviction_order(&entries, 20, &HashSet::new(), &played, None);
+        assert_eq!(victims, ["b", "c"]);
+    }
+
+    #[test]
+    fn stops_once_under_quota() {
+        let entries = [entry("a", 1, 30), entry("b", 2, 10), entry("c", 3, 10)];
+        assert_eq!(eviction_order(&entries, 25, &HashSet::new(), &HashMap::new(), None), ["a"]);
+        assert!(eviction_order(&entries, 50, &HashSet::new(), &HashMap::new(), None).is_empty());
+    }
+
+    #[test]
+    fn pinned_and_kept_maps_are_never_evicted() {
+        let entries = [entry("pinned", 1, 10), entry("new", 2, 10), entry("old", 3, 10)];
+        let pinned = HashSet::from(["pinned".to_string()]);
+        let victims = eviction_order(&entries, 0, &pinned, &HashMap::new(), Some("new"));
+        assert_eq!(victims, ["old"]);
+    }
+
+    #[test]
+    fn usage_and_pins_come_from_the_database() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        apply_migrations(&mut conn).unwrap();
+        record_play(&conn, "1a2b").unwrap();
+        assert!(last_played(&conn).unwrap()["1a2b"] >= now_secs() - 1);
+
+        conn.execute(
+            "INSERT INTO playlist_tracks (playlist_id, track_id, position, song_name, song_author_name,
+                level_author_name, bpm, duration, cover_url, diffs, added_at)
+             VALUES ('favorites', '3c4d', 0, '', '', '', 120, 60, '', '[]', '')",
+            [],
+        )
+        .unwrap();
+        assert_eq!(pinned_map_ids(&conn).unwrap(), HashSet::from(["3c4d".to_string()]));
+    }
+}
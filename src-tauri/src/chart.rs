@@ -0,0 +1,291 @@
+use serde::Deserialize;
+
+use crate::beatmap::{BeatMap, BeatMapNote, BeatMapObstacle, NoteType};
+use crate::error::AppError;
+
+// ---------------------------------------------------------------------------
+// Chart transforms: condense the 4-column grid to fewer lanes
+// ---------------------------------------------------------------------------
+
+pub const MAX_LANES: u8 = 4;
+/// Notes in one lane closer together than this are the same hit.
+const SIMULTANEOUS_SECS: f64 = 0.001;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BombPolicy {
+    /// Keep bombs unless a note lands in the same lane at the same time.
+    #[default]
+    Keep,
+    Drop,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ObstaclePolicy {
+    /// Remap obstacles onto the lanes their columns fall in.
+    #[default]
+    Keep,
+    Drop,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChartTransformOptions {
+    /// Target lane count, 1 to 4.
+    pub lanes: u8,
+    /// Minimum time between consecutive notes in one lane; 0 disables.
+    #[serde(default)]
+    pub min_gap_secs: f64,
+    #[serde(default)]
+    pub bombs: BombPolicy,
+    #[serde(default)]
+    pub obstacles: ObstaclePolicy,
+}
+
+/// Lane a grid column lands in. In 3-lane mode the two middle columns share
+/// the centre lane, as in the TypeScript condenser.
+fn lane_for_column(column: u8, lanes: u8) -> u8 {
+    match lanes {
+        1 => 0,
+        2 => column.min(3) / 2,
+        3 => match column {
+            0 => 0,
+            1 | 2 => 1,
+            _ => 2,
+        },
+        _ => column.min(3),
+    }
+}
+
+/// Map notes onto lanes, then keep at most one note per lane and moment
+/// (preferring notes over bombs) and drop notes that follow the previous one
+/// in their lane by less than `min_gap_secs`.
+fn condense_notes(notes: &[BeatMapNote], options: &ChartTransformOptions) -> Vec<BeatMapNote> {
+    let mut remapped: Vec<BeatMapNote> = notes
+        .iter()
+        .filter(|n| n.note_type != NoteType::Bomb || options.bombs == BombPolicy::Keep)
+        .map(|n| BeatMapNote {
+            column: lane_for_column(n.column, options.lanes),
+            ..n.clone()
+        })
+        .collect();
+    remapped.sort_by(|a, b| a.time.total_cmp(&b.time));
+
+    let mut kept: Vec<BeatMapNote> = Vec::with_capacity(remapped.len());
+    let mut last_in_lane: Vec<Option<usize>> = vec![None; options.lanes as usize];
+    for note in remapped {
+        let lane = note.column as usize;
+        if let Some(index) = last_in_lane[lane] {
+            let previous = &kept[index];
+            let gap = note.time - previous.time;
+            if gap < SIMULTANEOUS_SECS {
+                if previous.note_type == NoteType::Bomb && note.note_type != NoteType::Bomb {
+                    kept[index] = note;
+                }
+                continue;
+            }
+            if gap < options.min_gap_secs {
+                continue;
+            }
+        }
+        last_in_lane[lane] = Some(kept.len());
+        kept.push(note);
+    }
+    kept
+}
+
+fn condense_obstacles(obstacles: &[BeatMapObstacle], options: &ChartTransformOptions) -> Vec<BeatMapObstacle> {
+    if options.obstacles == ObstaclePolicy::Drop {
+        return Vec::new();
+    }
+    obstacles
+        .iter()
+        .map(|o| {
+            let first = lane_for_column(o.column, options.lanes);
+            let last = lane_for_column(o.column.saturating_add(o.width.max(1) - 1), options.lanes);
+            BeatMapObstacle {
+                column: first,
+                width: last - first + 1,
+                ..o.clone()
+            }
+        })
+        .collect()
+}
+
+/// Convert a 4-column chart to `options.lanes` lanes.
+pub fn transform(beatmap: &BeatMap, options: &ChartTransformOptions) -> Result<BeatMap, AppError> {
+    if options.lanes == 0 || options.lanes > MAX_LANES {
+        return Err(AppError::invalid_argument(format!(
+            "Lane count must be between 1 and {}, got {}",
+            MAX_LANES, options.lanes
+        )));
+    }
+    if !options.min_gap_secs.is_finite() || options.min_gap_secs < 0.0 {
+        return Err(AppError::invalid_argument("Minimum note gap must be zero or more seconds"));
+    }
+
+    Ok(BeatMap {
+        format: beatmap.format,
+        notes: condense_notes(&beatmap.notes, options),
+        obstacles: condense_obstacles(&beatmap.obstacles, options),
+        bpm: beatmap.bpm,
+    })
+}
+
+// ---------------------------------------------------------------------------
+// Tauri commands
+// ---------------------------------------------------------------------------
+
+/// Condense a parsed chart to fewer lanes without stacked or too-close notes.
+#[tauri::command]
+pub async fn chart_transform(beatmap: BeatMap, options: ChartTransformOptions) -> Result<BeatMap, AppError> {
+    transform(&beatmap, &options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn note(time: f64, column: u8, note_type: NoteType) -> BeatMapNote {
+        BeatMapNote {
+            time,
+            column,
+            row: 0,
+            note_type,
+            direction: 1,
+        }
+    }
+
+    fn chart(notes: Vec<BeatMapNote>, obstacles: Vec<BeatMapObstacle>) -> BeatMap {
+        BeatMap {
+            format: None,
+            notes,
+            obstacles,
+            bpm: 120.0,
+        }
+    }
+
+    fn options(lanes: u8) -> ChartTransformOptions {
+        ChartTransformOptions {
+            lanes,
+            min_gap_secs: 0.0,
+            bombs: BombPolicy::Keep,
+            obstacles: ObstaclePolicy::Keep,
+        }
+    }
+
+    fn lanes_of(map: &BeatMap) -> Vec<(f64, u8, NoteType)> {
+        map.notes.iter().map(|n| (n.time, n.column, n.note_type)).collect()
+    }
+
+    #[test]
+    fn columns_map_to_lanes() {
+        let lanes = |count| (0..4).map(|c| lane_for_column(c, count)).collect::<Vec<_>>();
+        assert_eq!(lanes(1), [0, 0, 0, 0]);
+        assert_eq!(lanes(2), [0, 0, 1, 1]);
+        assert_eq!(lanes(3), [0, 1, 1, 2]);
+        assert_eq!(lanes(4), [0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn simultaneous_notes_in_a_lane_collapse() {
+        let map = chart(
+            vec![
+                note(1.0, 0, NoteType::Left),
+                note(1.0005, 1, NoteType::Right),
+                note(1.0, 2, NoteType::Right),
+                note(2.0, 3, NoteType::Right),
+            ],
+            vec![],
+        );
+        let result = transform(&map, &options(2)).unwrap();
+        assert_eq!(
+            lanes_of(&result),
+            [(1.0, 0, NoteType::Left), (1.0, 1, NoteType::Right), (2.0, 1, NoteType::Right)]
+        );
+    }
+
+    #[test]
+    fn notes_win_over_bombs_in_the_same_moment() {
+        let map = chart(
+            vec![note(1.0, 0, NoteType::Bomb), note(1.0, 1, NoteType::Left), note(1.0, 2, NoteType::Bomb)],
+            vec![],
+        );
+        let result = transform(&map, &options(1)).unwrap();
+        assert_eq!(lanes_of(&result), [(1.0, 0, NoteType::Left)]);
+    }
+
+    #[test]
+    fn bombs_can_be_dropped() {
+        let map = chart(vec![note(1.0, 0, NoteType::Bomb), note(2.0, 0, NoteType::Right)], vec![]);
+        let options = ChartTransformOptions {
+            bombs: BombPolicy::Drop,
+            ..options(4)
+        };
+        assert_eq!(lanes_of(&transform(&map, &options).unwrap()), [(2.0, 0, NoteType::Right)]);
+    }
+
+    #[test]
+    fn min_gap_is_measured_from_the_last_kept_note() {
+        let map = chart(
+            (0..5).map(|i| note(i as f64 * 0.1, 0, NoteType::Left)).collect(),
+            vec![],
+        );
+        let options = ChartTransformOptions {
+            min_gap_secs: 0.15,
+            ..options(4)
+        };
+        let times: Vec<f64> = transform(&map, &options).unwrap().notes.iter().map(|n| n.time).collect();
+        assert_eq!(times, [0.0, 0.2, 0.4]);
+    }
+
+    #[test]
+    fn min_gap_is_per_lane() {
+        let map = chart(vec![note(0.0, 0, NoteType::Left), note(0.05, 3, NoteType::Right)], vec![]);
+        let options = ChartTransformOptions {
+            min_gap_secs: 0.1,
+            ..options(2)
+        };
+        assert_eq!(transform(&map, &options).unwrap().notes.len(), 2);
+    }
+
+    #[test]
+    fn obstacles_span_the_lanes_they_cover() {
+        let wall = |column, width| BeatMapObstacle {
+            time: 1.0,
+            duration: 0.5,
+            column,
+            width,
+        };
+        let map = chart(vec![], vec![wall(0, 1), wall(1, 2), wall(2, 3)]);
+        let spans: Vec<(u8, u8)> = transform(&map, &options(3))
+            .unwrap()
+            .obstacles
+            .iter()
+            .map(|o| (o.column, o.width))
+            .collect();
+        assert_eq!(spans, [(0, 1), (1, 1), (1, 2)]);
+
+        let options = ChartTransformOptions {
+            obstacles: ObstaclePolicy::Drop,
+            ..options(3)
+        };
+        assert!(transform(&map, &options).unwrap().obstacles.is_empty());
+    }
+
+    #[test]
+    fn rejects_invalid_options() {
+        let map = chart(vec![], vec![]);
+        for lanes in [0, MAX_LANES + 1] {
+            assert!(matches!(transform(&map, &options(lanes)), Err(AppError::InvalidArgument { .. })));
+        }
+        for gap in [-1.0, f64::NAN] {
+            let options = ChartTransformOptions {
+                min_gap_secs: gap,
+                ..options(2)
+            };
+            assert!(matches!(transform(&map, &options), Err(AppError::InvalidArgument { .. })));
+        }
+    }
+}
@@ -0,0 +1,436 @@
+use serde::Serialize;
+use tauri::ipc::Request;
+
+use crate::audio::{self, DecodedAudio};
+use crate::beatmap::{BeatMap, BeatMapNote, NoteType};
+use crate::chart::MAX_LANES;
+use crate::error::{run_blocking, AppError};
+use crate::spectrum;
+use crate::tempo::{self, TempoAnalysis};
+
+// ---------------------------------------------------------------------------
+// Chart generation from spectral-flux onsets
+// ---------------------------------------------------------------------------
+//
+// Onsets are peaks of the full-range flux envelope, snapped to the tracked
+// beat grid. Which band dominates an onset (bass, mids, highs) picks the lane
+// from left to right, and the strongest onsets become doubles on harder
+// difficulties.
+
+/// Headers carrying the options of a raw `chart_generate` request.
+pub const DIFFICULTY_HEADER: &str = "x-chart-difficulty";
+pub const LANES_HEADER: &str = "x-chart-lanes";
+
+/// Bass, mids and highs, mapped to lanes from left to right.
+const MID_BAND_HZ: (f32, f32) = (200.0, 2000.0);
+const HIGH_BAND_LOW_HZ: f32 = 2000.0;
+/// Frames either side an onset must be the maximum of.
+const PEAK_RADIUS_FRAMES: usize = 3;
+/// Window of the moving average an onset must rise above.
+const AVERAGE_WINDOW_SECS: f64 = 0.1;
+/// Onsets further than this share of a grid step from the grid are dropped.
+const SNAP_TOLERANCE: f64 = 0.25;
+/// Notes in one lane closer than this are moved to a neighbouring lane.
+const JACK_SECS: f64 = 0.3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Difficulty {
+    Easy,
+    Normal,
+    Hard,
+    Expert,
+}
+
+struct DifficultyProfile {
+    /// Height an onset must rise above its surroundings, in envelope std devs.
+    threshold: f64,
+    min_gap_secs: f64,
+    max_notes_per_sec: f64,
+    /// Grid steps per beat onsets are snapped to.
+    subdivision: usize,
+    /// Share of the strongest onsets played as two notes.
+    double_share: f64,
+}
+
+impl Difficulty {
+    fn parse(value: &str) -> Result<Self, AppError> {
+        match value.to_ascii_lowercase().as_str() {
+            "easy" => Ok(Self::Easy),
+            "normal" => Ok(Self::Normal),
+            "hard" => Ok(Self::Hard),
+            "expert" => Ok(Self::Expert),
+            _ => Err(AppError::invalid_argument(format!("Unknown difficulty {}", value))),
+        }
+    }
+
+    fn profile(self) -> DifficultyProfile {
+        match self {
+            Self::Easy => DifficultyProfile {
+                threshold: 1.0,
+                min_gap_secs: 0.45,
+                max_notes_per_sec: 1.2,
+                subdivision: 1,
+                double_share: 0.0,
+            },
+            Self::Normal => DifficultyProfile {
+                threshold: 0.7,
+                min_gap_secs: 0.3,
+                max_notes_per_sec: 2.0,
+                subdivision: 2,
+                double_share: 0.0,
+            },
+            Self::Hard => DifficultyProfile {
+                threshold: 0.5,
+                min_gap_secs: 0.18,
+                max_notes_per_sec: 3.2,
+                subdivision: 4,
+                double_share: 0.05,
+            },
+            Self::Expert => DifficultyProfile {
+                threshold: 0.35,
+                min_gap_secs: 0.11,
+                max_notes_per_sec: 4.8,
+                subdivision: 4,
+                double_share: 0.1,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GeneratedChart {
+    pub beat_map: BeatMap,
+    pub tempo: TempoAnalysis,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Onset {
+    time: f64,
+    frame: usize,
+    strength: f64,
+}
+
+/// Local maxima of the envelope that rise `threshold` above its moving average.
+fn pick_onsets(envelope: &[f64], frame_rate: f64, threshold: f64) -> Vec<Onset> {
+    let average_radius = ((AVERAGE_WINDOW_SECS * frame_rate).round() as usize).max(1);
+    (0..envelope.len())
+        .filter_map(|t| {
+            let window = |radius: usize| &envelope[t.saturating_sub(radius)..(t + radius + 1).min(envelope.len())];
+            let value = envelope[t];
+            let is_peak = window(PEAK_RADIUS_FRAMES).iter().all(|&v| v <= value);
+            let around = window(average_radius);
+            let average = around.iter().sum::<f64>() / around.len() as f64;
+            (is_peak && value > 0.0 && value >= average + threshold).then_some(Onset {
+                time: t as f64 / frame_rate,
+                frame: t,
+                strength: value - average,
+            })
+        })
+        .collect()
+}
+
+/// Grid points `subdivision` per tracked beat.
+fn beat_grid(beats: &[f64], subdivision: usize) -> Vec<f64> {
+    let mut grid: Vec<f64> = beats
+        .windows(2)
+        .flat_map(|w| (0..subdivision).map(move |k| w[0] + (w[1] - w[0]) * k as f64 / subdivision as f64))
+        .collect();
+    grid.extend(beats.last());
+    grid
+}
+
+/// Move onsets onto the nearest grid point, dropping those too far off the
+/// grid. Without a grid (no beats tracked) onsets keep their own times.
+fn snap_to_grid(onsets: Vec<Onset>, grid: &[f64]) -> Vec<Onset> {
+    if grid.len() < 2 {
+        return onsets;
+    }
+    onsets
+        .into_iter()
+        .filter_map(|onset| {
+            let index = grid.partition_point(|&g| g < onset.time);
+            let nearest = [index.checked_sub(1), Some(index)]
+                .into_iter()
+                .flatten()
+                .filter_map(|i| grid.get(i).map(|&g| (i, g)))
+                .min_by(|a, b| (a.1 - onset.time).abs().total_cmp(&(b.1 - onset.time).abs()))?;
+            let (i, point) = nearest;
+            let step = if i + 1 < grid.len() { grid[i + 1] - point } else { point - grid[i - 1] };
+            ((point - onset.time).abs() <= step * SNAP_TOLERANCE).then_some(Onset { time: point, ..onset })
+        })
+        .collect()
+}
+
+/// Keep the strongest onsets that respect the minimum gap and note budget,
+/// in time order.
+fn thin_onsets(mut onsets: Vec<Onset>, min_gap_secs: f64, max_notes: usize) -> Vec<Onset> {
+    onsets.sort_by(|a, b| b.strength.total_cmp(&a.strength));
+    let mut kept: Vec<Onset> = Vec::new();
+    for onset in onsets {
+        if kept.len() >= max_notes {
+            break;
+        }
+        if kept.iter().all(|k| (k.time - onset.time).abs() >= min_gap_secs) {
+            kept.push(onset);
+        }
+    }
+    kept.sort_by(|a, b| a.time.total_cmp(&b.time));
+    kept
+}
+
+/// Position from 0 (bass) to 1 (highs) of the band energy around a frame.
+fn band_position(bands: &[Vec<f64>; 3], frame: usize) -> f64 {
+    let energy: Vec<f64> = bands
+        .iter()
+        .map(|band| {
+            band[frame.saturating_sub(2)..(frame + 3).min(band.len())]
+                .iter()
+                .cloned()
+                .fold(0.0, f64::max)
+        })
+        .collect();
+    let total: f64 = energy.iter().sum();
+    if total <= 0.0 {
+        0.5
+    } else {
+        (energy[1] * 0.5 + energy[2]) / total
+    }
+}
+
+fn note(time: f64, lane: usize, lanes: usize, position: f64) -> BeatMapNote {
+    let centre = (lanes - 1) as f64 / 2.0;
+    let left = (lane as f64) < centre || (lane as f64 == centre && position < 0.5);
+    BeatMapNote {
+        time,
+        column: lane as u8,
+        row: 0,
+        note_type: if left { NoteType::Left } else { NoteType::Right },
+        direction: 0,
+    }
+}
+
+/// Lane per onset by the rank of its band position, so bass-heavy onsets go
+/// left and bright ones right while every lane gets a similar share whatever
+/// the song's overall spectral balance.
+fn lanes_by_rank(positions: &[f64], lanes: usize) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..positions.len()).collect();
+    order.sort_by(|&a, &b| positions[a].total_cmp(&positions[b]));
+    let mut assigned = vec![0; positions.len()];
+    for (rank, &index) in order.iter().enumerate() {
+        assigned[index] = (rank * lanes / positions.len()).min(lanes - 1);
+    }
+    assigned
+}
+
+/// Lay out notes for `lanes` lanes: band position picks the lane, repeated
+/// notes in one lane are spread out, and the strongest onsets get a second
+/// note in the mirrored lane.
+fn assign_lanes(onsets: &[Onset], bands: &[Vec<f64>; 3], lanes: usize, double_share: f64) -> Vec<BeatMapNote> {
+    let mut strengths: Vec<f64> = onsets.iter().map(|o| o.strength).collect();
+    strengths.sort_by(|a, b| b.total_cmp(a));
+    let doubles = (onsets.len() as f64 * double_share).floor() as usize;
+    let double_threshold = strengths.get(doubles.saturating_sub(1)).copied().unwrap_or(f64::INFINITY);
+
+    let positions: Vec<f64> = onsets.iter().map(|o| band_position(bands, o.frame)).collect();
+    let ranked = lanes_by_rank(&positions, lanes);
+
+    let mut notes = Vec::with_capacity(onsets.len());
+    let mut previous: Option<(usize, f64)> = None;
+    for ((onset, &position), &ranked_lane) in onsets.iter().zip(&positions).zip(&ranked) {
+        let mut lane = ranked_lane;
+        if let Some((previous_lane, previous_time)) = previous {
+            if lanes > 1 && lane == previous_lane && onset.time - previous_time < JACK_SECS {
+                lane = if lane + 1 < lanes { lane + 1 } else { lane - 1 };
+            }
+        }
+        notes.push(note(onset.time, lane, lanes, position));
+
+        if doubles > 0 && lanes > 1 && onset.strength >= double_threshold {
+            let mirrored = lanes - 1 - lane;
+            let second = if mirrored != lane { mirrored } else { lane + 1 };
+            notes.push(note(onset.time, second, lanes, position));
+        }
+        previous = Some((lane, onset.time));
+    }
+    notes
+}
+
+/// Generate a chart for `lanes` lanes (columns 0 to `lanes - 1`).
+pub fn generate(audio: &DecodedAudio, difficulty: Difficulty, lanes: u8) -> Result<GeneratedChart, AppError> {
+    if lanes == 0 || lanes > MAX_LANES {
+        return Err(AppError::invalid_argument(format!(
+            "Lane count must be between 1 and {}, got {}",
+            MAX_LANES, lanes
+        )));
+    }
+    let profile = difficulty.profile();
+    let nyquist = audio.sample_rate as f32 / 2.0;
+    let flux = spectrum::spectral_flux(
+        &audio.samples,
+        audio.sample_rate,
+        &[(0.0, nyquist), tempo::LOW_BAND_HZ, MID_BAND_HZ, (HIGH_BAND_LOW_HZ, nyquist)],
+    );
+    let envelope = tempo::normalize(&flux.bands[0]);
+    let bands = [
+        tempo::normalize(&flux.bands[1]),
+        tempo::normalize(&flux.bands[2]),
+        tempo::normalize(&flux.bands[3]),
+    ];
+    let tempo = tempo::analyze_envelopes(&envelope, &bands[0], flux.frame_rate, audio.duration_secs());
+
+    let onsets = pick_onsets(&envelope, flux.frame_rate, profile.threshold);
+    let onsets = snap_to_grid(onsets, &beat_grid(&tempo.beats, profile.subdivision));
+    let max_notes = (profile.max_notes_per_sec * audio.duration_secs()).ceil() as usize;
+    let onsets = thin_onsets(onsets, profile.min_gap_secs, max_notes);
+    let notes = assign_lanes(&onsets, &bands, lanes as usize, profile.double_share);
+
+    Ok(GeneratedChart {
+        beat_map: BeatMap {
+            format: None,
+            notes,
+            obstacles: Vec::new(),
+            bpm: tempo.bpm,
+        },
+        tempo,
+    })
+}
+
+// ---------------------------------------------------------------------------
+// Tauri commands
+// ---------------------------------------------------------------------------
+
+/// Decode an audio file sent as the raw request body and generate a chart
+/// from its onsets. Difficulty and lane count come from the
+/// `x-chart-difficulty` and `x-chart-lanes` headers.
+#[tauri::command]
+pub async fn chart_generate(request: Request<'_>) -> Result<GeneratedChart, AppError> {
+    let (bytes, extension) = audio::request_audio(&request)?;
+    let difficulty = Difficulty::parse(audio::header(&request, DIFFICULTY_HEADER).unwrap_or("normal"))?;
+    let lanes = match audio::header(&request, LANES_HEADER) {
+        Some(value) => value
+            .parse::<u8>()
+            .map_err(|_| AppError::invalid_argument(format!("Invalid lane count {}", value)))?,
+        None => MAX_LANES,
+    };
+    run_blocking(move || {
+        let audio = audio::decode_mono(bytes, extension.as_deref())?;
+        generate(&audio, difficulty, lanes)
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tempo::tests::{beat_times, click_track, SAMPLE_RATE};
+
+    fn onset(time: f64, strength: f64) -> Onset {
+        Onset {
+            time,
+            frame: 0,
+            strength,
+        }
+    }
+
+    fn click_audio(beats: &[f64]) -> DecodedAudio {
+        DecodedAudio {
+            samples: click_track(beats, 0),
+            sample_rate: SAMPLE_RATE,
+        }
+    }
+
+    #[test]
+    fn difficulty_names() {
+        assert_eq!(Difficulty::parse("Hard").unwrap(), Difficulty::Hard);
+        assert!(matches!(Difficulty::parse("insane"), Err(AppError::InvalidArgument { .. })));
+    }
+
+    #[test]
+    fn grid_subdivides_each_beat() {
+        assert_eq!(beat_grid(&[1.0, 2.0, 4.0], 2), [1.0, 1.5, 2.0, 3.0, 4.0]);
+        assert!(beat_grid(&[], 4).is_empty());
+    }
+
+    #[test]
+    fn onsets_snap_to_nearby_grid_points_only() {
+        let grid = [0.0, 0.5, 1.0, 1.5];
+        let snapped = snap_to_grid(vec![onset(0.48, 1.0), onset(0.75, 1.0), onset(1.6, 1.0)], &grid);
+        let times: Vec<f64> = snapped.iter().map(|o| o.time).collect();
+        assert_eq!(times, [0.5, 1.5]);
+        // Without a grid onsets are kept as they are
+        assert_eq!(snap_to_grid(vec![onset(0.75, 1.0)], &[]).len(), 1);
+    }
+
+    #[test]
+    fn thinning_keeps_the_strongest_within_gap_and_budget() {
+        let onsets = vec![onset(0.0, 1.0), onset(0.1, 3.0), onset(0.5, 2.0), onset(1.0, 0.5)];
+        let times = |kept: Vec<Onset>| kept.iter().map(|o| o.time).collect::<Vec<_>>();
+        assert_eq!(times(thin_onsets(onsets.clone(), 0.3, 10)), [0.1, 0.5, 1.0]);
+        assert_eq!(times(thin_onsets(onsets, 0.3, 2)), [0.1, 0.5]);
+    }
+
+    #[test]
+    fn ranks_spread_evenly_over_lanes() {
+        let positions = [0.9, 0.1, 0.5, 0.3, 0.7, 0.2];
+        assert_eq!(lanes_by_rank(&positions, 3), [2, 0, 1, 1, 2, 0]);
+        assert_eq!(lanes_by_rank(&positions, 1), [0; 6]);
+    }
+
+    #[test]
+    fn quick_repeats_move_to_a_neighbouring_lane() {
+        let bands = [vec![1.0; 10], vec![0.0; 10], vec![0.0; 10]];
+        let onsets = [onset(0.0, 1.0), onset(0.1, 1.0), onset(1.0, 1.0)];
+        let lanes: Vec<u8> = assign_lanes(&onsets, &bands, 4, 0.0).iter().map(|n| n.column).collect();
+        // Equal band positions rank in onset order
+        assert_eq!(lanes, [0, 1, 2]);
+
+        // Only the strongest onset becomes a double, in the mirrored lane
+        let onsets = [onset(0.0, 1.0), onset(0.5, 2.0), onset(1.0, 1.0)];
+        let doubled: Vec<(f64, u8)> = assign_lanes(&onsets, &bands, 4, 0.5)
+            .iter()
+            .map(|n| (n.time, n.column))
+            .collect();
+        assert_eq!(doubled, [(0.0, 0), (0.5, 1), (0.5, 2), (1.0, 2)]);
+    }
+
+    #[test]
+    fn click_track_chart_follows_the_beat() {
+        let beats = beat_times(120.0, 40, 0.5);
+        let audio = click_audio(&beats);
+        let chart = generate(&audio, Difficulty::Normal, 4).unwrap();
+
+        assert!((chart.tempo.bpm - 120.0).abs() < 1.0, "bpm {}", chart.tempo.bpm);
+        assert!(chart.beat_map.notes.len() >= beats.len() / 2, "{} notes", chart.beat_map.notes.len());
+        let mut previous = f64::MIN;
+        for note in &chart.beat_map.notes {
+            assert!(note.column < 4);
+            assert!(note.time >= previous);
+            previous = note.time;
+            // Every click is on a beat, so notes land on tracked beats
+            let nearest = chart.tempo.beats.iter().map(|b| (b - note.time).abs()).fold(f64::MAX, f64::min);
+            assert!(nearest < 1e-9, "note at {} is off the beat", note.time);
+        }
+    }
+
+    #[test]
+    fn harder_difficulties_place_at_least_as_many_notes() {
+        let audio = click_audio(&beat_times(150.0, 40, 0.5));
+        let counts: Vec<usize> = [Difficulty::Easy, Difficulty::Normal, Difficulty::Hard, Difficulty::Expert]
+            .into_iter()
+            .map(|d| generate(&audio, d, 3).unwrap().beat_map.notes.len())
+            .collect();
+        assert!(counts.windows(2).all(|w| w[0] <= w[1]), "{:?}", counts);
+    }
+
+    #[test]
+    fn rejects_invalid_lane_counts() {
+        let audio = click_audio(&beat_times(120.0, 4, 0.5));
+        for lanes in [0, MAX_LANES + 1] {
+            assert!(matches!(
+                generate(&audio, Difficulty::Easy, lanes),
+                Err(AppError::InvalidArgument { .. })
+            ));
+        }
+    }
+}
@@ -0,0 +1,170 @@
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use rusqlite::types::{Value as SqlValue, ValueRef};
+use rusqlite::{params_from_iter, Connection};
+use serde::Serialize;
+use serde_json::Value as JsonValue;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager};
+
+use crate::error::AppError;
+
+// ---------------------------------------------------------------------------
+// Result types (matching TypeScript RawQueryResult / ExecuteResult in api/db.ts)
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RawQueryResult {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<JsonValue>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExecuteResult {
+    pub rows_affected: usize,
+    pub last_insert_id: Option<i64>,
+}
+
+// ---------------------------------------------------------------------------
+// Managed connection state
+// ---------------------------------------------------------------------------
+
+pub struct Database {
+    pub conn: Mutex<Connection>,
+}
+
+const DB_FILENAME: &str = "moon-tapper.db";
+
+impl Database {
+    /// Open (or create) the app database under `app_data_dir`.
+    pub fn open(app_handle: &AppHandle) -> Result<Self, AppError> {
+        let app_dir = app_handle
+            .path()
+            .app_data_dir()
+            .map_err(|e| AppError::io(format!("Failed to get app data directory: {}", e)))?;
+        std::fs::create_dir_all(&app_dir)
+            .map_err(|e| AppError::io(format!("Failed to create app data directory: {}", e)))?;
+
+        let conn = Connection::open(app_dir.join(DB_FILENAME))
+            .map_err(|e| AppError::io(format!("Failed to open database: {}", e)))?;
+        conn.execute_batch("PRAGMA journal_mode = WAL; PRAGMA foreign_keys = ON;")
+            .map_err(|e| AppError::io(format!("Failed to configure database: {}", e)))?;
+
+        Ok(Self {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    pub fn lock(&self) -> Result<std::sync::MutexGuard<'_, Connection>, AppError> {
+        self.conn
+            .lock()
+            .map_err(|_| AppError::internal("Database connection lock poisoned"))
+    }
+}
+
+// ---------------------------------------------------------------------------
+// JSON <-> SQLite value conversion
+// ---------------------------------------------------------------------------
+
+fn json_to_sql(value: &JsonValue) -> SqlValue {
+    match value {
+        JsonValue::Null => SqlValue::Null,
+        JsonValue::Bool(b) => SqlValue::Integer(*b as i64),
+        JsonValue::Number(n) => match n.as_i64() {
+            Some(i) => SqlValue::Integer(i),
+            None => SqlValue::Real(n.as_f64().unwrap_or_default()),
+        },
+        JsonValue::String(s) => SqlValue::Text(s.clone()),
+        // Nested structures are stored as their JSON text
+        JsonValue::Array(_) | JsonValue::Object(_) => SqlValue::Text(value.to_string()),
+    }
+}
+
+fn sql_to_json(value: ValueRef<'_>) -> JsonValue {
+    match value {
+        ValueRef::Null => JsonValue::Null,
+        ValueRef::Integer(i) => JsonValue::from(i),
+        ValueRef::Real(f) => JsonValue::from(f),
+        ValueRef::Text(t) => JsonValue::String(String::from_utf8_lossy(t).into_owned()),
+        ValueRef::Blob(b) => JsonValue::String(BASE64.encode(b)),
+    }
+}
+
+fn is_insert_statement(sql: &str) -> bool {
+    let head = sql.trim_start().to_ascii_uppercase();
+    head.starts_with("INSERT") || head.starts_with("REPLACE")
+}
+
+// ---------------------------------------------------------------------------
+// Query helpers (shared with other backend modules)
+// ---------------------------------------------------------------------------
+
+pub fn run_query(
+    conn: &Connection,
+    sql: &str,
+    params: &[JsonValue],
+) -> Result<RawQueryResult, AppError> {
+    let mut stmt = conn
+        .prepare(sql)
+        .map_err(|e| AppError::invalid_argument(format!("Prepare failed: {}", e)))?;
+
+    let columns: Vec<String> = stmt.column_names().iter().map(|c| c.to_string()).collect();
+    let column_count = columns.len();
+
+    let mut rows_iter = stmt.query(params_from_iter(params.iter().map(json_to_sql)))?;
+
+    let mut rows: Vec<Vec<JsonValue>> = Vec::new();
+    while let Some(row) = rows_iter.next()? {
+        let mut values = Vec::with_capacity(column_count);
+        for i in 0..column_count {
+            values.push(sql_to_json(row.get_ref(i)?));
+        }
+        rows.push(values);
+    }
+
+    Ok(RawQueryResult { columns, rows })
+}
+
+pub fn run_execute(
+    conn: &Connection,
+    sql: &str,
+    params: &[JsonValue],
+) -> Result<ExecuteResult, AppError> {
+    let rows_affected = conn.execute(sql, params_from_iter(params.iter().map(json_to_sql)))?;
+
+    let last_insert_id = if rows_affected > 0 && is_insert_statement(sql) {
+        Some(conn.last_insert_rowid())
+    } else {
+        None
+    };
+
+    Ok(ExecuteResult {
+        rows_affected,
+        last_insert_id,
+    })
+}
+
+// ---------------------------------------------------------------------------
+// Tauri commands
+// ---------------------------------------------------------------------------
+
+/// Execute a SELECT query with positional `?` params.
+#[tauri::command]
+pub fn db_query(
+    sql: String,
+    params: Vec<JsonValue>,
+    db: tauri::State<'_, Database>,
+) -> Result<RawQueryResult, AppError> {
+    let conn = db.lock()?;
+    run_query(&conn, &sql, &params)
+}
+
+/// Execute an INSERT/UPDATE/DELETE statement with positional `?` params.
+#[tauri::command]
+pub fn db_execute(
+    sql: String,
+    params: Vec<JsonValue>,
+    db: tauri::State<'_, Database>,
+) -> Result<ExecuteResult, AppError> {
+    let conn = db.lock()?;
+    run_execute(&conn, &sql, &params)
+}
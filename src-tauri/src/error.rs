@@ -0,0 +1,198 @@
+use serde::Serialize;
+
+// ---------------------------------------------------------------------------
+// Structured command error
+// ---------------------------------------------------------------------------
+
+/// Error returned to the frontend. Serializes as `{ "kind": "...", "message": "...", ... }`
+/// so the UI can branch on `kind` instead of matching message text.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case", rename_all_fields = "camelCase")]
+pub enum AppError {
+    /// The request never got a response (DNS, connect, timeout, TLS).
+    Network { message: String },
+    /// Offline mode is on and the data is not available locally.
+    Offline { message: String },
+    /// The server answered with a non-success status not covered below.
+    HttpStatus { status: u16, message: String },
+    /// The map (or endpoint) does not exist, e.g. a deleted map.
+    NotFound { message: String },
+    /// Still throttled after retries; `retry_after_secs` comes from `Retry-After`.
+    RateLimited {
+        retry_after_secs: Option<u64>,
+        message: String,
+    },
+    /// The response body could not be decoded.
+    InvalidResponse { message: String },
+    ZipInvalid { message: String },
+    /// Info.dat or a difficulty file could not be parsed.
+    InvalidBeatmap { message: String },
+    /// The ZIP exceeds an extraction limit; `limit` names the setting.
+    ZipLimitExceeded { limit: String, message: String },
+    MissingAudio { message: String },
+    MissingInfoDat { message: String },
+    /// The downloaded files don't hash to the BeatSaver version hash.
+    HashMismatch {
+        expected: String,
+        actual: String,
+        message: String,
+    },
+    Io { message: String },
+    /// The audio could not be probed or decoded.
+    AudioDecode { message: String },
+    CacheCorrupt { message: String },
+    /// A command argument is out of range.
+    InvalidArgument { message: String },
+    /// Unexpected backend failure (e.g. a worker that has shut down).
+    Internal { message: String },
+}
+
+impl AppError {
+    pub fn network(message: impl Into<String>) -> Self {
+        Self::Network {
+            message: message.into(),
+        }
+    }
+
+    pub fn offline(message: impl Into<String>) -> Self {
+        Self::Offline {
+            message: message.into(),
+        }
+    }
+
+    pub fn zip_invalid(message: impl Into<String>) -> Self {
+        Self::ZipInvalid {
+            message: message.into(),
+        }
+    }
+
+    pub fn invalid_beatmap(message: impl Into<String>) -> Self {
+        Self::InvalidBeatmap {
+            message: message.into(),
+        }
+    }
+
+    pub fn zip_limit(limit: &str, message: impl Into<String>) -> Self {
+        Self::ZipLimitExceeded {
+            limit: limit.to_string(),
+            message: message.into(),
+        }
+    }
+
+    pub fn io(message: impl Into<String>) -> Self {
+        Self::Io {
+            message: message.into(),
+        }
+    }
+
+    pub fn audio_decode(message: impl Into<String>) -> Self {
+        Self::AudioDecode {
+            message: message.into(),
+        }
+    }
+
+    pub fn cache_corrupt(message: impl Into<String>) -> Self {
+        Self::CacheCorrupt {
+            message: message.into(),
+        }
+    }
+
+    pub fn invalid_argument(message: impl Into<String>) -> Self {
+        Self::InvalidArgument {
+            message: message.into(),
+        }
+    }
+
+    pub fn internal(message: impl Into<String>) -> Self {
+        Self::Internal {
+            message: message.into(),
+        }
+    }
+
+    pub fn invalid_response(message: impl Into<String>) -> Self {
+        Self::InvalidResponse {
+            message: message.into(),
+        }
+    }
+
+    /// Map an unsuccessful HTTP status to the matching error kind.
+    pub fn from_status(status: reqwest::StatusCode, retry_after_secs: Option<u64>, context: &str) -> Self {
+        let message = format!("{} failed: HTTP {}", context, status);
+        match status {
+            reqwest::StatusCode::NOT_FOUND => Self::NotFound { message },
+            reqwest::StatusCode::TOO_MANY_REQUESTS => Self::RateLimited {
+                retry_after_secs,
+                message,
+            },
+            _ => Self::HttpStatus {
+                status: status.as_u16(),
+                message,
+            },
+        }
+    }
+
+    /// Whether a stale cached copy is an acceptable answer to this failure.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Self::Network { .. } | Self::RateLimited { .. } => true,
+            Self::HttpStatus { status, .. } => *status >= 500,
+            _ => false,
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            Self::Network { message }
+            | Self::Offline { message }
+            | Self::HttpStatus { message, .. }
+            | Self::NotFound { message }
+            | Self::RateLimited { message, .. }
+            | Self::InvalidResponse { message }
+            | Self::ZipInvalid { message }
+            | Self::InvalidBeatmap { message }
+            | Self::ZipLimitExceeded { message, .. }
+            | Self::MissingAudio { message }
+            | Self::MissingInfoDat { message }
+            | Self::HashMismatch { message, .. }
+            | Self::Io { message }
+            | Self::AudioDecode { message }
+            | Self::CacheCorrupt { message }
+            | Self::InvalidArgument { message }
+            | Self::Internal { message } => message,
+        }
+    }
+}
+
+impl std::fmt::Display for AppError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.message())
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl From<std::io::Error> for AppError {
+    fn from(e: std::io::Error) -> Self {
+        Self::io(e.to_string())
+    }
+}
+
+impl From<rusqlite::Error> for AppError {
+    fn from(e: rusqlite::Error) -> Self {
+        Self::io(format!("Database error: {}", e))
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Blocking work
+// ---------------------------------------------------------------------------
+
+/// Run file I/O, decoding or parsing on the blocking pool, so it stalls
+/// neither the async runtime nor (for synchronous commands) the main thread.
+pub async fn run_blocking<T: Send + 'static>(
+    task: impl FnOnce() -> Result<T, AppError> + Send + 'static,
+) -> Result<T, AppError> {
+    tauri::async_runtime::spawn_blocking(task)
+        .await
+        .map_err(|e| AppError::internal(format!("Background task failed: {}", e)))?
+}
@@ -0,0 +1,364 @@
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tokio::time::Instant;
+
+use reqwest::header::RETRY_AFTER;
+use reqwest::{Response, StatusCode};
+
+use crate::error::AppError;
+use crate::settings::HttpSettings;
+
+// ---------------------------------------------------------------------------
+// Shared HTTP client (managed Tauri state)
+// ---------------------------------------------------------------------------
+
+const USER_AGENT: &str = concat!("moon-tapper/", env!("CARGO_PKG_VERSION"));
+
+/// Idle pooled connections are dropped after this long.
+const POOL_IDLE_TIMEOUT: Duration = Duration::from_secs(90);
+const POOL_MAX_IDLE_PER_HOST: usize = 8;
+/// Slowest positive request rate; slower rates would wait for ages (or
+/// overflow a `Duration`) between requests.
+pub const MIN_RATE_PER_SEC: f64 = 0.01;
+
+/// Holds the one client every network command reuses. Rebuilt when the HTTP
+/// settings change; handles are cheap to clone.
+pub struct HttpClient(RwLock<HttpHandle>);
+
+/// A configured `reqwest::Client` plus the retry policy and rate limiter that
+/// wrap every request made through it.
+#[derive(Clone)]
+pub struct HttpHandle {
+    client: reqwest::Client,
+    retry: RetryPolicy,
+    limiter: Arc<RateLimiter>,
+}
+
+impl HttpClient {
+    pub fn new(settings: &HttpSettings) -> Result<Self, AppError> {
+        Ok(Self(RwLock::new(build_handle(settings)?)))
+    }
+
+    pub fn handle(&self) -> HttpHandle {
+        match self.0.read() {
+            Ok(handle) => handle.clone(),
+            Err(poisoned) => poisoned.into_inner().clone(),
+        }
+    }
+
+    /// Replace the client with one built from new settings.
+    pub fn reconfigure(&self, settings: &HttpSettings) -> Result<(), AppError> {
+        let handle = build_handle(settings)?;
+        *self
+            .0
+            .write()
+            .map_err(|_| AppError::internal("HTTP client lock poisoned"))? = handle;
+        Ok(())
+    }
+}
+
+fn build_handle(settings: &HttpSettings) -> Result<HttpHandle, AppError> {
+    let mut builder = reqwest::Client::builder()
+        .user_agent(USER_AGENT)
+        .connect_timeout(Duration::from_secs(settings.connect_timeout_secs))
+        .read_timeout(Duration::from_secs(settings.read_timeout_secs))
+        .pool_idle_timeout(POOL_IDLE_TIMEOUT)
+        .pool_max_idle_per_host(POOL_MAX_IDLE_PER_HOST);
+
+    if let Some(proxy_url) = settings.proxy.as_deref().filter(|p| !p.trim().is_empty()) {
+        let proxy = reqwest::Proxy::all(proxy_url.trim())
+            .map_err(|e| AppError::invalid_argument(format!("Invalid proxy {}: {}", proxy_url, e)))?;
+        builder = builder.proxy(proxy);
+    }
+
+    let client = builder
+        .build()
+        .map_err(|e| AppError::internal(format!("Failed to build HTTP client: {}", e)))?;
+
+    Ok(HttpHandle {
+        client,
+        retry: RetryPolicy {
+            max_attempts: settings.max_attempts.max(1),
+            base_delay: Duration::from_millis(settings.retry_base_delay_ms),
+            max_delay: Duration::from_millis(settings.retry_max_delay_ms),
+        },
+        limiter: Arc::new(RateLimiter::new(
+            settings.rate_limit_per_sec,
+            settings.rate_limit_burst,
+        )),
+    })
+}
+
+impl HttpHandle {
+    /// GET a URL, waiting for a rate-limit token before every attempt and
+    /// retrying connection failures, 429 and 5xx responses with backoff.
+    /// The last response is returned as is once attempts run out.
+    pub async fn fetch(&self, url: &str) -> Result<Response, reqwest::Error> {
+        self.with_retries(|| self.client.get(url).send()).await
+    }
+
+    /// The rate limiting and retry loop of `fetch`, around any request.
+    async fn with_retries<F, Fut>(&self, mut send: F) -> Result<Response, reqwest::Error>
+    where
+        F: FnMut() -> Fut,
+        Fut: std::future::Future<Output = Result<Response, reqwest::Error>>,
+    {
+        let mut attempt = 1;
+        loop {
+            self.limiter.acquire().await;
+            let result = send().await;
+
+            let retry_after = match &result {
+                Ok(response) if is_retryable_status(response.status()) => {
+                    Some(retry_after_secs(response).map(Duration::from_secs))
+                }
+                Ok(_) => return result,
+                Err(e) if e.is_connect() || e.is_timeout() => Some(None),
+                Err(_) => return result,
+            };
+
+            if attempt >= self.retry.max_attempts {
+                return result;
+            }
+            let delay = retry_after
+                .flatten()
+                .map(|d| d.min(self.retry.max_delay))
+                .unwrap_or_else(|| self.retry.backoff(attempt));
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+}
+
+fn is_retryable_status(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+/// Parse a `Retry-After` header given in seconds.
+pub fn retry_after_secs(response: &Response) -> Option<u64> {
+    response
+        .headers()
+        .get(RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse::<u64>()
+        .ok()
+}
+
+// ---------------------------------------------------------------------------
+// Retry policy
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, Clone)]
+struct RetryPolicy {
+    max_attempts: u32,
+    base_delay: Duration,
+    max_delay: Duration,
+}
+
+impl RetryPolicy {
+    /// Exponential backoff for the given (1-based) attempt with jitter in the
+    /// upper half of the window, so parallel retries don't line up.
+    fn backoff(&self, attempt: u32) -> Duration {
+        let exp = self
+            .base_delay
+            .saturating_mul(1u32 << (attempt - 1).min(16))
+            .min(self.max_delay);
+        let half = exp / 2;
+        half + half.mul_f64(fastrand::f64())
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Token bucket rate limiter
+// ---------------------------------------------------------------------------
+
+struct RateLimiter {
+    rate: f64,
+    burst: f64,
+    state: tokio::sync::Mutex<BucketState>,
+}
+
+struct BucketState {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl RateLimiter {
+    fn new(rate_per_sec: f64, burst: u32) -> Self {
+        let burst = f64::from(burst.max(1));
+        Self {
+            // Settings loaded from disk skip validation, so clamp here too
+            rate: if rate_per_sec > 0.0 {
+                rate_per_sec.max(MIN_RATE_PER_SEC)
+            } else {
+                0.0
+            },
+            burst,
+            state: tokio::sync::Mutex::new(BucketState {
+                tokens: burst,
+                last_refill: Instant::now(),
+            }),
+        }
+    }
+
+    /// Wait until a token is available and take it. A non-positive rate
+    /// disables limiting.
+    async fn acquire(&self) {
+        if self.rate <= 0.0 {
+            return;
+        }
+        loop {
+            let wait = {
+                let mut state = self.state.lock().await;
+                let now = Instant::now();
+                let elapsed = now.duration_since(state.last_refill).as_secs_f64();
+                state.tokens = (state.tokens + elapsed * self.rate).min(self.burst);
+                state.last_refill = now;
+
+                if state.tokens >= 1.0 {
+                    state.tokens -= 1.0;
+                    return;
+                }
+                Duration::from_secs_f64((1.0 - state.tokens) / self.rate)
+            };
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    fn handle(max_attempts: u32, base_delay_ms: u64, max_delay_ms: u64, rate_limit_per_sec: f64) -> HttpHandle {
+        build_handle(&HttpSettings {
+            connect_timeout_secs: 30,
+            read_timeout_secs: 30,
+            proxy: None,
+            max_attempts,
+            retry_base_delay_ms: base_delay_ms,
+            retry_max_delay_ms: max_delay_ms,
+            rate_limit_per_sec,
+            rate_limit_burst: 1,
+        })
+        .unwrap()
+    }
+
+    /// Send through `with_retries`, answering with one `(status, retry-after)`
+    /// per attempt and repeating the last. Returns the final status and the
+    /// time of each attempt since the start.
+    async fn run(handle: &HttpHandle, answers: &[(u16, Option<&str>)]) -> (StatusCode, Vec<Duration>) {
+        let start = Instant::now();
+        let attempts = Mutex::new(Vec::new());
+        let response = handle
+            .with_retries(|| {
+                let mut attempts = attempts.lock().unwrap();
+                let (status, retry_after) = answers[attempts.len().min(answers.len() - 1)];
+                attempts.push(start.elapsed());
+                let mut builder = tauri::http::Response::builder().status(status);
+                if let Some(value) = retry_after {
+                    builder = builder.header(RETRY_AFTER, value);
+                }
+                let response = Response::from(builder.body(Vec::<u8>::new()).unwrap());
+                async move { Ok(response) }
+            })
+            .await
+            .unwrap();
+        (response.status(), attempts.into_inner().unwrap())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn retries_server_errors_with_backoff() {
+        let (status, attempts) = run(&handle(3, 1000, 60_000, 0.0), &[(503, None), (500, None), (200, None)]).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(attempts.len(), 3);
+        // Jittered backoff waits half to all of 1s, then of 2s
+        let first = attempts[1] - attempts[0];
+        let second = attempts[2] - attempts[1];
+        assert!(first >= Duration::from_millis(500) && first <= Duration::from_secs(1), "{:?}", first);
+        assert!(second >= Duration::from_secs(1) && second <= Duration::from_secs(2), "{:?}", second);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn waits_for_retry_after_up_to_the_max_delay() {
+        let (_, attempts) = run(&handle(2, 1, 60_000, 0.0), &[(429, Some("7")), (200, None)]).await;
+        assert_eq!(attempts, [Duration::ZERO, Duration::from_secs(7)]);
+
+        let (_, attempts) = run(&handle(2, 1, 10_000, 0.0), &[(503, Some(" 100 ")), (200, None)]).await;
+        assert_eq!(attempts, [Duration::ZERO, Duration::from_secs(10)]);
+
+        // An unparsable Retry-After falls back to backoff
+        let (_, attempts) = run(&handle(2, 1000, 60_000, 0.0), &[(429, Some("soon")), (200, None)]).await;
+        assert!(attempts[1] <= Duration::from_secs(1));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn returns_the_last_response_when_attempts_run_out() {
+        let (status, attempts) = run(&handle(4, 10, 100, 0.0), &[(502, None)]).await;
+        assert_eq!(status, StatusCode::BAD_GATEWAY);
+        assert_eq!(attempts.len(), 4);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn client_errors_are_not_retried() {
+        let (status, attempts) = run(&handle(5, 10, 100, 0.0), &[(404, None), (200, None)]).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(attempts.len(), 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn every_attempt_waits_for_a_token() {
+        // Burst 1 at 2/s: retries are spaced by the limiter, not the 1ms backoff
+        let (_, attempts) = run(&handle(3, 1, 1, 2.0), &[(500, None)]).await;
+        assert_eq!(
+            attempts,
+            [Duration::ZERO, Duration::from_millis(500), Duration::from_millis(1000)]
+        );
+    }
+
+    #[test]
+    fn backoff_doubles_within_the_cap() {
+        let policy = RetryPolicy {
+            max_attempts: 10,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_millis(1000),
+        };
+        for (attempt, window) in [(1, 100), (2, 200), (3, 400), (4, 800), (5, 1000), (30, 1000)] {
+            let delay = policy.backoff(attempt).as_millis();
+            assert!(delay >= window / 2 && delay <= window, "attempt {}: {}ms", attempt, delay);
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn bucket_allows_a_burst_then_the_rate() {
+        let limiter = RateLimiter::new(2.0, 3);
+        let start = Instant::now();
+        for _ in 0..3 {
+            limiter.acquire().await;
+        }
+        assert_eq!(start.elapsed(), Duration::ZERO);
+        limiter.acquire().await;
+        limiter.acquire().await;
+        assert_eq!(start.elapsed(), Duration::from_secs(1));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn tiny_rates_are_clamped() {
+        let limiter = RateLimiter::new(1e-300, 1);
+        limiter.acquire().await;
+        let start = Instant::now();
+        limiter.acquire().await;
+        assert_eq!(start.elapsed(), Duration::from_secs_f64(1.0 / MIN_RATE_PER_SEC));
+
+        // A non-positive rate never waits
+        let unlimited = RateLimiter::new(0.0, 1);
+        let start = Instant::now();
+        for _ in 0..10 {
+            unlimited.acquire().await;
+        }
+        assert_eq!(start.elapsed(), Duration::ZERO);
+    }
+}
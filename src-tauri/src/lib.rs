@@ -1,4 +1,23 @@
+mod audio;
+mod beatmap;
 mod beatsaver;
+mod cache;
+mod chart;
+mod chart_generator;
+mod db;
+mod error;
+mod http;
+mod map_cache;
+mod map_info;
+mod map_protocol;
+mod metadata_cache;
+mod migrations;
+mod playlists;
+mod preview;
+mod scores;
+mod settings;
+mod spectrum;
+mod tempo;
 
 #[cfg(desktop)]
 use tauri::menu::{Menu, MenuItemBuilder, PredefinedMenuItem, SubmenuBuilder};
@@ -8,8 +27,9 @@ use tauri::Manager;
 
 /// Write a text file to a given path
 #[tauri::command]
-fn write_text_file(path: String, content: String) -> Result<(), String> {
-    std::fs::write(&path, &content).map_err(|e| e.to_string())
+fn write_text_file(path: String, content: String) -> Result<(), error::AppError> {
+    std::fs::write(&path, &content)?;
+    Ok(())
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -18,6 +38,7 @@ pub fn run() {
         .plugin(tauri_plugin_shell::init())
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_store::Builder::new().build())
+        .register_asynchronous_uri_scheme_protocol(map_protocol::SCHEME, map_protocol::handle)
         .invoke_handler(tauri::generate_handler![
             beatsaver::beatsaver_download,
             beatsaver::beatsaver_search,
@@ -26,6 +47,37 @@ pub fn run() {
             beatsaver::beatsaver_has_download,
             beatsaver::beatsaver_download_track,
             beatsaver::beatsaver_fetch_track,
+            cache::cache_stats,
+            cache::cache_list,
+            cache::cache_delete,
+            cache::cache_purge,
+            cache::cache_verify,
+            beatmap::beatmap_info,
+            beatmap::beatmap_parse,
+            chart::chart_transform,
+            chart_generator::chart_generate,
+            audio::audio_probe,
+            audio::audio_probe_map,
+            preview::audio_preview,
+            preview::audio_preview_map,
+            db::db_query,
+            db::db_execute,
+            migrations::db_schema_version,
+            scores::scores_submit,
+            scores::scores_for_map,
+            scores::scores_personal_best,
+            scores::scores_recent,
+            playlists::playlists_list,
+            playlists::playlists_create,
+            playlists::playlists_rename,
+            playlists::playlists_reorder,
+            playlists::playlists_delete,
+            playlists::playlists_add_track,
+            playlists::playlists_remove_track,
+            playlists::playlists_reorder_tracks,
+            playlists::playlists_import_local_storage,
+            settings::settings_get,
+            settings::settings_set,
             write_text_file
         ]);
 
@@ -69,6 +121,26 @@ pub fn run() {
 
     builder
         .setup(|app| {
+            // Open the app database and bring its schema up to date
+            let database = db::Database::open(app.handle())?;
+            migrations::apply_migrations(&mut *database.lock()?)?;
+            app.manage(database);
+
+            // Load persisted backend settings and build the shared HTTP client
+            let backend_settings = settings::SettingsState::load(app.handle());
+            let http_client = http::HttpClient::new(&backend_settings.get().http)
+                .or_else(|_| http::HttpClient::new(&settings::HttpSettings::default()))?;
+            app.manage(backend_settings);
+            app.manage(http_client);
+
+            // Clean up interrupted cache writes and convert downloads cached
+            // in the old single-JSON format
+            let cache_handle = app.handle().clone();
+            tauri::async_runtime::spawn_blocking(move || {
+                map_cache::recover_entries(&cache_handle);
+                map_cache::migrate_legacy_entries(&cache_handle);
+            });
+
             // Initialize track download queue worker
             let (track_tx, track_rx) = tokio::sync::mpsc::unbounded_channel();
             app.manage(beatsaver::TrackQueue { tx: track_tx });
@@ -0,0 +1,643 @@
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager};
+
+use crate::beatsaver::{compute_version_hash, is_hash_unverifiable, BeatSaverMapData, ExtractedFile, ExtractedMap};
+use crate::error::AppError;
+use crate::metadata_cache::now_secs;
+
+// ---------------------------------------------------------------------------
+// On-disk layout
+// ---------------------------------------------------------------------------
+//
+// downloads/<map_id>/
+//     manifest.json      written last; its presence marks a complete entry
+//     Info.dat
+//     <difficulty>.dat   one per beatmap, original ZIP names
+//     <audio>            e.g. song.egg
+//     <cover>            e.g. cover.jpg (optional)
+//
+// Entries are written to a `.tmp-*` sibling and renamed into place, so a
+// crash never leaves a half-written `<map_id>` directory behind.
+
+const MANIFEST_FILENAME: &str = "manifest.json";
+/// Broken entries are moved here instead of failing on every load.
+const QUARANTINE_DIR: &str = ".quarantine";
+/// In-progress writes and replaced versions awaiting removal.
+const TEMP_PREFIX: &str = ".tmp-";
+const OLD_PREFIX: &str = ".old-";
+pub const INFO_FILENAME: &str = "Info.dat";
+const MANIFEST_FORMAT: u32 = 1;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MapManifest {
+    pub format: u32,
+    pub map_id: String,
+    pub audio_filename: String,
+    pub cover_filename: Option<String>,
+    pub beatmap_filenames: Vec<String>,
+    pub downloaded_at: i64,
+    pub size_bytes: u64,
+    /// BeatSaver version hash of the files, when known.
+    #[serde(default)]
+    pub version_hash: Option<String>,
+    /// Whether `version_hash` was computed from the files themselves (and
+    /// checked against the download URL's hash) rather than taken from the URL.
+    #[serde(default)]
+    pub hash_verified: bool,
+    /// The map's format has no computable version hash (Info.dat v4), so its
+    /// files were never checked against `version_hash`.
+    #[serde(default)]
+    pub hash_unverifiable: bool,
+    /// Problems found while resolving files through Info.dat at download time.
+    #[serde(default)]
+    pub warnings: Vec<String>,
+    /// Set on a manifest served in place of a newer version that couldn't be
+    /// downloaded. Never read back from disk.
+    #[serde(default, skip_deserializing)]
+    pub outdated: bool,
+}
+
+pub fn downloads_dir(app_handle: &AppHandle) -> PathBuf {
+    let app_dir = app_handle
+        .path()
+        .app_data_dir()
+        .expect("Failed to get app data directory");
+    app_dir.join("downloads")
+}
+
+/// Directory of a map entry. Every path built from a map id goes through
+/// here or `legacy_json_path`, so the id is checked before it touches the
+/// filesystem.
+pub fn map_dir(app_handle: &AppHandle, map_id: &str) -> Result<PathBuf, AppError> {
+    check_map_id(map_id)?;
+    Ok(downloads_dir(app_handle).join(map_id))
+}
+
+fn legacy_json_path(app_handle: &AppHandle, map_id: &str) -> Result<PathBuf, AppError> {
+    check_map_id(map_id)?;
+    Ok(downloads_dir(app_handle).join(format!("{}.json", map_id)))
+}
+
+/// Version hash from a BeatSaver CDN URL (`.../<hash>.zip`).
+pub fn version_hash_from_url(download_url: &str) -> Option<String> {
+    let stem = Path::new(reqwest::Url::parse(download_url).ok()?.path())
+        .file_stem()?
+        .to_str()?
+        .to_ascii_lowercase();
+    (stem.len() == 40 && stem.chars().all(|c| c.is_ascii_hexdigit())).then_some(stem)
+}
+
+/// Reduce a ZIP entry name to a plain file name so it can't escape the map
+/// directory.
+pub fn safe_filename(name: &str) -> Option<String> {
+    let file_name = Path::new(name).file_name()?.to_str()?;
+    if file_name.is_empty() || file_name == "." || file_name == ".." {
+        return None;
+    }
+    Some(file_name.to_string())
+}
+
+/// Reject map ids that aren't a single plain path component, so an id can
+/// never name the downloads directory itself, a `.`-prefixed bookkeeping
+/// directory or anything outside the cache.
+pub fn check_map_id(map_id: &str) -> Result<(), AppError> {
+    if safe_filename(map_id).as_deref() == Some(map_id) && !map_id.starts_with('.') {
+        Ok(())
+    } else {
+        Err(AppError::invalid_argument(format!("Invalid map id {:?}", map_id)))
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Lookup
+// ---------------------------------------------------------------------------
+
+/// Whether a map is cached, and if so whether it is the wanted version.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CacheStatus {
+    Missing,
+    Cached,
+    /// Cached from an older version than the one asked for.
+    Outdated,
+    /// Cached and current, but in a format whose files can't be checked
+    /// against the version hash.
+    Unverifiable,
+}
+
+impl MapManifest {
+    /// A manifest without a recorded hash (e.g. a migrated legacy entry)
+    /// matches any version, so old caches are not all re-downloaded at once.
+    pub fn matches_version(&self, version_hash: Option<&str>) -> bool {
+        match (self.version_hash.as_deref(), version_hash) {
+            (Some(cached), Some(wanted)) => cached.eq_ignore_ascii_case(wanted),
+            _ => true,
+        }
+    }
+}
+
+/// Cache status of a map against the wanted version hash, if known.
+pub fn cache_status(
+    app_handle: &AppHandle,
+    map_id: &str,
+    version_hash: Option<&str>,
+) -> Result<CacheStatus, AppError> {
+    Ok(match read_manifest(app_handle, map_id)? {
+        None => CacheStatus::Missing,
+        Some(manifest) if !manifest.matches_version(version_hash) => CacheStatus::Outdated,
+        Some(manifest) if manifest.hash_unverifiable => CacheStatus::Unverifiable,
+        Some(_) => CacheStatus::Cached,
+    })
+}
+
+/// Ids of every complete map entry in the download cache.
+pub fn downloaded_map_ids(app_handle: &AppHandle) -> Vec<String> {
+    let Ok(entries) = std::fs::read_dir(downloads_dir(app_handle)) else {
+        return Vec::new();
+    };
+    entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().join(MANIFEST_FILENAME).exists())
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .collect()
+}
+
+/// Ids of every entry directory, complete or not.
+pub fn entry_dir_ids(app_handle: &AppHandle) -> Vec<String> {
+    let Ok(entries) = std::fs::read_dir(downloads_dir(app_handle)) else {
+        return Vec::new();
+    };
+    entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().is_dir())
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .filter(|name| !name.starts_with('.'))
+        .collect()
+}
+
+/// Manifests of every complete map entry; unreadable entries are skipped.
+pub fn list_manifests(app_handle: &AppHandle) -> Vec<MapManifest> {
+    downloaded_map_ids(app_handle)
+        .iter()
+        .filter_map(|id| read_manifest(app_handle, id).ok().flatten())
+        .collect()
+}
+
+/// Remove a map's directory (and any legacy JSON file) from the cache.
+pub fn delete_map(app_handle: &AppHandle, map_id: &str) -> Result<(), AppError> {
+    let dir = map_dir(app_handle, map_id)?;
+    if dir.exists() {
+        std::fs::remove_dir_all(&dir)
+            .map_err(|e| AppError::io(format!("Failed to delete map {}: {}", map_id, e)))?;
+    }
+    let legacy = legacy_json_path(app_handle, map_id)?;
+    if legacy.exists() {
+        std::fs::remove_file(&legacy)
+            .map_err(|e| AppError::io(format!("Failed to delete map {}: {}", map_id, e)))?;
+    }
+    Ok(())
+}
+
+/// Remove every map entry, including incomplete and legacy ones. Returns the
+/// number of entries removed.
+pub fn delete_all(app_handle: &AppHandle) -> Result<usize, AppError> {
+    let mut ids = entry_dir_ids(app_handle);
+    ids.extend(legacy_entry_ids(app_handle));
+    ids.sort();
+    ids.dedup();
+    for id in &ids {
+        delete_map(app_handle, id)?;
+    }
+    let quarantine = downloads_dir(app_handle).join(QUARANTINE_DIR);
+    if quarantine.exists() {
+        std::fs::remove_dir_all(&quarantine)
+            .map_err(|e| AppError::io(format!("Failed to clear quarantined entries: {}", e)))?;
+    }
+    Ok(ids.len())
+}
+
+// ---------------------------------------------------------------------------
+// Loaders (read only the files needed)
+// ---------------------------------------------------------------------------
+
+fn read_file(path: &Path) -> Result<Vec<u8>, AppError> {
+    std::fs::read(path)
+        .map_err(|e| AppError::cache_corrupt(format!("Failed to read {}: {}", path.display(), e)))
+}
+
+fn read_text(path: &Path) -> Result<String, AppError> {
+    String::from_utf8(read_file(path)?)
+        .map_err(|e| AppError::cache_corrupt(format!("{} is not valid UTF-8: {}", path.display(), e)))
+}
+
+/// Read a map's manifest, migrating a legacy `<id>.json` entry first if needed.
+/// An unreadable manifest quarantines the entry and reads as a cache miss.
+pub fn read_manifest(app_handle: &AppHandle, map_id: &str) -> Result<Option<MapManifest>, AppError> {
+    let dir = map_dir(app_handle, map_id)?;
+    let path = dir.join(MANIFEST_FILENAME);
+    if !path.exists() && !migrate_legacy_entry(app_handle, map_id)? {
+        return Ok(None);
+    }
+    let parsed = read_text(&path).and_then(|json| {
+        serde_json::from_str(&json)
+            .map_err(|e| AppError::cache_corrupt(format!("Failed to parse manifest for {}: {}", map_id, e)))
+    });
+    match parsed {
+        Ok(manifest) => Ok(Some(manifest)),
+        Err(_) => {
+            quarantine(app_handle, &dir, map_id)?;
+            Ok(None)
+        }
+    }
+}
+
+/// Read the Info.dat of a downloaded map.
+pub fn read_info_dat(app_handle: &AppHandle, map_id: &str) -> Result<String, AppError> {
+    if read_manifest(app_handle, map_id)?.is_none() {
+        return Err(AppError::NotFound {
+            message: format!("Map {} is not downloaded", map_id),
+        });
+    }
+    read_text(&map_dir(app_handle, map_id)?.join(INFO_FILENAME))
+}
+
+/// Read a difficulty file listed in a map's manifest (matched
+/// case-insensitively, like Info.dat references).
+pub fn read_beatmap(app_handle: &AppHandle, map_id: &str, filename: &str) -> Result<String, AppError> {
+    let not_found = || AppError::NotFound {
+        message: format!("Map {} has no difficulty file {}", map_id, filename),
+    };
+    let manifest = read_manifest(app_handle, map_id)?.ok_or_else(not_found)?;
+    let name = manifest
+        .beatmap_filenames
+        .into_iter()
+        .find(|f| f.eq_ignore_ascii_case(filename))
+        .ok_or_else(not_found)?;
+    read_text(&map_dir(app_handle, map_id)?.join(name))
+}
+
+/// Read a downloaded map's audio file, returning its bytes and filename.
+pub fn read_audio(app_handle: &AppHandle, map_id: &str) -> Result<(Vec<u8>, String), AppError> {
+    let manifest = read_manifest(app_handle, map_id)?.ok_or_else(|| AppError::NotFound {
+        message: format!("Map {} is not downloaded", map_id),
+    })?;
+    let bytes = read_file(&map_dir(app_handle, map_id)?.join(&manifest.audio_filename))?;
+    Ok((bytes, manifest.audio_filename))
+}
+
+/// Path of a file derived from a downloaded map (e.g. transcoded audio),
+/// stored beside the map's own files. Derived files aren't in the manifest;
+/// `disk_size` counts them, and they go away with the entry.
+pub fn derived_path(app_handle: &AppHandle, map_id: &str, filename: &str) -> Result<PathBuf, AppError> {
+    if read_manifest(app_handle, map_id)?.is_none() {
+        return Err(AppError::NotFound {
+            message: format!("Map {} is not downloaded", map_id),
+        });
+    }
+    Ok(map_dir(app_handle, map_id)?.join(filename))
+}
+
+/// Write a derived file through a temporary name, so readers never see it
+/// half-written.
+pub fn write_derived(app_handle: &AppHandle, map_id: &str, filename: &str, bytes: &[u8]) -> Result<PathBuf, AppError> {
+    let path = derived_path(app_handle, map_id, filename)?;
+    let temp = map_dir(app_handle, map_id)?.join(format!("{}{}", TEMP_PREFIX, filename));
+    write_file(&temp, bytes)?;
+    std::fs::rename(&temp, &path).map_err(|e| {
+        let _ = std::fs::remove_file(&temp);
+        AppError::io(format!("Failed to move {} into place: {}", path.display(), e))
+    })?;
+    Ok(path)
+}
+
+/// Files in an entry's directory that the manifest doesn't list, by name.
+pub fn derived_files(app_handle: &AppHandle, manifest: &MapManifest) -> Vec<(String, std::fs::Metadata)> {
+    let Ok(entries) = map_dir(app_handle, &manifest.map_id).and_then(|dir| Ok(std::fs::read_dir(dir)?)) else {
+        return Vec::new();
+    };
+    let own = |name: &str| {
+        name == MANIFEST_FILENAME
+            || name == INFO_FILENAME
+            || name == manifest.audio_filename
+            || manifest.cover_filename.as_deref() == Some(name)
+            || manifest.beatmap_filenames.iter().any(|f| f == name)
+    };
+    entries
+        .flatten()
+        .filter_map(|entry| Some((entry.file_name().into_string().ok()?, entry.metadata().ok()?)))
+        .filter(|(name, metadata)| metadata.is_file() && !own(name))
+        .collect()
+}
+
+/// Bytes an entry takes on disk: the map's own files plus derived files.
+pub fn disk_size(app_handle: &AppHandle, manifest: &MapManifest) -> u64 {
+    let derived: u64 = derived_files(app_handle, manifest).iter().map(|(_, m)| m.len()).sum();
+    manifest.size_bytes + derived
+}
+
+/// Read a JSON file and check that it parses, returning its size.
+fn check_json(path: &Path) -> Result<u64, AppError> {
+    let text = read_text(path)?;
+    serde_json::from_str::<serde_json::Value>(&text)
+        .map_err(|e| AppError::cache_corrupt(format!("{} is not valid JSON: {}", path.display(), e)))?;
+    Ok(text.len() as u64)
+}
+
+/// Re-open every file of an entry: Info.dat and the beatmaps must parse, the
+/// audio and cover must be readable, and the total size must match the
+/// manifest (catching truncated writes). Returns the checked manifest.
+pub fn verify_map(app_handle: &AppHandle, map_id: &str) -> Result<MapManifest, AppError> {
+    let Some(manifest) = read_manifest(app_handle, map_id)? else {
+        return Err(AppError::cache_corrupt(format!("Map {} has no manifest", map_id)));
+    };
+    let dir = map_dir(app_handle, map_id)?;
+    let mut size_bytes = check_json(&dir.join(INFO_FILENAME))?;
+    for filename in &manifest.beatmap_filenames {
+        size_bytes += check_json(&dir.join(filename))?;
+    }
+    size_bytes += read_file(&dir.join(&manifest.audio_filename))?.len() as u64;
+    if let Some(cover) = &manifest.cover_filename {
+        size_bytes += read_file(&dir.join(cover))?.len() as u64;
+    }
+    if size_bytes != manifest.size_bytes {
+        return Err(AppError::cache_corrupt(format!(
+            "Map {} is {} bytes on disk but its manifest records {}",
+            map_id, size_bytes, manifest.size_bytes
+        )));
+    }
+    Ok(manifest)
+}
+
+// ---------------------------------------------------------------------------
+// Writer
+// ---------------------------------------------------------------------------
+
+/// Write a file and flush it to disk before returning.
+fn write_file(path: &Path, bytes: &[u8]) -> Result<(), AppError> {
+    let mut file = File::create(path)
+        .map_err(|e| AppError::io(format!("Failed to create {}: {}", path.display(), e)))?;
+    file.write_all(bytes)
+        .and_then(|_| file.sync_all())
+        .map_err(|e| AppError::io(format!("Failed to write {}: {}", path.display(), e)))
+}
+
+/// Flush a directory's entries (new names, renames) to disk. Directories
+/// can't be opened for syncing on Windows, where renames are durable anyway.
+fn sync_dir(path: &Path) {
+    #[cfg(unix)]
+    if let Ok(dir) = File::open(path) {
+        let _ = dir.sync_all();
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+}
+
+fn sibling_path(downloads: &Path, prefix: &str, map_id: &str) -> PathBuf {
+    downloads.join(format!("{}{}-{:08x}", prefix, map_id, fastrand::u32(..)))
+}
+
+/// Write every file of an entry into `dir`, manifest last.
+fn write_entry(
+    dir: &Path,
+    map_id: &str,
+    version_hash: Option<String>,
+    map: &ExtractedMap,
+) -> Result<MapManifest, AppError> {
+    std::fs::create_dir_all(dir)
+        .map_err(|e| AppError::io(format!("Failed to create map directory: {}", e)))?;
+
+    let mut size_bytes = 0u64;
+    let mut write = |name: &str, bytes: &[u8]| -> Result<(), AppError> {
+        write_file(&dir.join(name), bytes)?;
+        size_bytes += bytes.len() as u64;
+        Ok(())
+    };
+
+    write(INFO_FILENAME, map.info_dat.as_bytes())?;
+    let mut beatmap_filenames = Vec::with_capacity(map.beatmaps.len());
+    for (name, content) in &map.beatmaps {
+        write(name, content.as_bytes())?;
+        beatmap_filenames.push(name.clone());
+    }
+    beatmap_filenames.sort();
+    write(&map.audio.name, &map.audio.bytes)?;
+    if let Some(cover) = &map.cover {
+        write(&cover.name, &cover.bytes)?;
+    }
+
+    let manifest = MapManifest {
+        format: MANIFEST_FORMAT,
+        map_id: map_id.to_string(),
+        audio_filename: map.audio.name.clone(),
+        cover_filename: map.cover.as_ref().map(|c| c.name.clone()),
+        beatmap_filenames,
+        downloaded_at: now_secs(),
+        size_bytes,
+        version_hash: map.hash.clone().or(version_hash),
+        hash_verified: map.hash_verified,
+        hash_unverifiable: map.hash_unverifiable,
+        warnings: map.warnings.clone(),
+        outdated: false,
+    };
+    let json = serde_json::to_vec_pretty(&manifest)
+        .map_err(|e| AppError::io(format!("Failed to serialize manifest: {}", e)))?;
+    write_file(&dir.join(MANIFEST_FILENAME), &json)?;
+    sync_dir(dir);
+
+    Ok(manifest)
+}
+
+/// Write an extracted map into its directory. The entry is built in a
+/// temporary sibling and renamed into place, replacing any older version only
+/// once the new one is complete on disk.
+pub fn save_map(
+    app_handle: &AppHandle,
+    map_id: &str,
+    version_hash: Option<String>,
+    map: &ExtractedMap,
+) -> Result<MapManifest, AppError> {
+    let dir = map_dir(app_handle, map_id)?;
+    let downloads = downloads_dir(app_handle);
+    let temp = sibling_path(&downloads, TEMP_PREFIX, map_id);
+    let manifest = match write_entry(&temp, map_id, version_hash, map) {
+        Ok(manifest) => manifest,
+        Err(e) => {
+            let _ = std::fs::remove_dir_all(&temp);
+            return Err(e);
+        }
+    };
+
+    let old = sibling_path(&downloads, OLD_PREFIX, map_id);
+    let replacing = dir.exists();
+    if replacing {
+        if let Err(e) = std::fs::rename(&dir, &old) {
+            let _ = std::fs::remove_dir_all(&temp);
+            return Err(AppError::io(format!("Failed to replace map {}: {}", map_id, e)));
+        }
+    }
+    if let Err(e) = std::fs::rename(&temp, &dir) {
+        if replacing {
+            let _ = std::fs::rename(&old, &dir);
+        }
+        let _ = std::fs::remove_dir_all(&temp);
+        return Err(AppError::io(format!("Failed to move map {} into place: {}", map_id, e)));
+    }
+    sync_dir(&downloads);
+    if replacing {
+        let _ = std::fs::remove_dir_all(&old);
+    }
+
+    Ok(manifest)
+}
+
+// ---------------------------------------------------------------------------
+// Crash recovery and quarantine
+// ---------------------------------------------------------------------------
+
+/// Move a broken entry (directory or legacy file) into `.quarantine` so it is
+/// treated as missing and re-downloaded instead of failing on every load.
+fn quarantine(app_handle: &AppHandle, path: &Path, map_id: &str) -> Result<(), AppError> {
+    if !path.exists() {
+        return Ok(());
+    }
+    let quarantine_dir = downloads_dir(app_handle).join(QUARANTINE_DIR);
+    std::fs::create_dir_all(&quarantine_dir)
+        .map_err(|e| AppError::io(format!("Failed to create quarantine directory: {}", e)))?;
+    let mut target = format!("{}-{}", map_id, now_secs());
+    if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
+        target = format!("{}.{}", target, ext);
+    }
+    std::fs::rename(path, quarantine_dir.join(target))
+        .map_err(|e| AppError::io(format!("Failed to quarantine map {}: {}", map_id, e)))
+}
+
+/// Clean up after a crash: drop leftover temporary and replaced directories
+/// and quarantine entry directories that never got a manifest.
+pub fn recover_entries(app_handle: &AppHandle) {
+    let downloads = downloads_dir(app_handle);
+    let Ok(entries) = std::fs::read_dir(&downloads) else {
+        return;
+    };
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if !path.is_dir() {
+            continue;
+        }
+        if name.starts_with(TEMP_PREFIX) || name.starts_with(OLD_PREFIX) {
+            let _ = std::fs::remove_dir_all(&path);
+        } else if !name.starts_with('.') && !path.join(MANIFEST_FILENAME).exists() {
+            let _ = quarantine(app_handle, &path, &name);
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Migration from the legacy `<id>.json` (base64-inside-JSON) format
+// ---------------------------------------------------------------------------
+
+/// Pick the file name Info.dat gives for a field, falling back to a default.
+fn info_filename(info: &serde_json::Value, key: &str, fallback: &str) -> String {
+    info.get(key)
+        .and_then(|v| v.as_str())
+        .and_then(safe_filename)
+        .unwrap_or_else(|| fallback.to_string())
+}
+
+/// Convert one legacy entry to the directory layout and remove the JSON file.
+/// Returns false when there is no legacy entry. Unreadable entries are
+/// quarantined.
+fn migrate_legacy_entry(app_handle: &AppHandle, map_id: &str) -> Result<bool, AppError> {
+    let path = legacy_json_path(app_handle, map_id)?;
+    if !path.exists() {
+        return Ok(false);
+    }
+
+    let converted = read_text(&path).and_then(|json| {
+        let data: BeatSaverMapData = serde_json::from_str(&json)
+            .map_err(|e| AppError::cache_corrupt(format!("Failed to parse legacy cache: {}", e)))?;
+        let info: serde_json::Value = serde_json::from_str(&data.info_dat).unwrap_or_default();
+        let decode = |b64: &str| {
+            BASE64
+                .decode(b64)
+                .map_err(|e| AppError::cache_corrupt(format!("Invalid base64 in legacy cache: {}", e)))
+        };
+
+        let beatmaps = data
+            .beatmaps
+            .into_iter()
+            .filter_map(|(name, content)| Some((safe_filename(&name)?, content)))
+            .collect();
+        Ok(ExtractedMap {
+            hash: compute_version_hash(&data.info_dat, &beatmaps),
+            // Legacy entries never recorded the URL they came from, so the
+            // computed hash was never checked against anything
+            hash_verified: false,
+            hash_unverifiable: is_hash_unverifiable(&data.info_dat),
+            warnings: Vec::new(),
+            audio: ExtractedFile {
+                name: info_filename(&info, "_songFilename", "song.egg"),
+                bytes: decode(&data.audio_base64)?,
+            },
+            cover: match &data.cover_base64 {
+                Some(b64) => Some(ExtractedFile {
+                    name: info_filename(&info, "_coverImageFilename", "cover.jpg"),
+                    bytes: decode(b64)?,
+                }),
+                None => None,
+            },
+            beatmaps,
+            info_dat: data.info_dat,
+        })
+    });
+
+    match converted {
+        Ok(map) => {
+            save_map(app_handle, map_id, None, &map)?;
+            std::fs::remove_file(&path)
+                .map_err(|e| AppError::io(format!("Failed to remove legacy cache file: {}", e)))?;
+            Ok(true)
+        }
+        Err(_) => {
+            quarantine(app_handle, &path, map_id)?;
+            Ok(false)
+        }
+    }
+}
+
+fn legacy_entry_ids(app_handle: &AppHandle) -> Vec<String> {
+    let Ok(entries) = std::fs::read_dir(downloads_dir(app_handle)) else {
+        return Vec::new();
+    };
+    entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "json"))
+        .filter_map(|path| Some(path.file_stem()?.to_string_lossy().into_owned()))
+        .collect()
+}
+
+/// Migrate every legacy `<id>.json` entry in the downloads directory.
+pub fn migrate_legacy_entries(app_handle: &AppHandle) {
+    for id in legacy_entry_ids(app_handle) {
+        let _ = migrate_legacy_entry(app_handle, &id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn map_ids_must_be_plain_names() {
+        assert!(check_map_id("1a2b3").is_ok());
+        for id in ["", ".", "..", "../x", "a/b", "/tmp", ".quarantine", ".tmp-1a2b3"] {
+            assert!(check_map_id(id).is_err(), "accepted {:?}", id);
+        }
+    }
+}
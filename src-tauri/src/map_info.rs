@@ -0,0 +1,425 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::error::AppError;
+
+// ---------------------------------------------------------------------------
+// Normalized Info.dat model (mirrors TypeScript BeatMapInfo)
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InfoFormat {
+    /// `_songFilename`, `_difficultyBeatmapSets` (used by v2 and v3 maps)
+    V2,
+    /// `audio.songFilename`, flat `difficultyBeatmaps`
+    V4,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MapInfo {
+    pub format: InfoFormat,
+    pub song_name: String,
+    pub song_sub_name: String,
+    pub song_author_name: String,
+    pub level_author_name: String,
+    pub bpm: f64,
+    pub song_filename: String,
+    pub cover_filename: Option<String>,
+    /// v4 only: the BPMInfo/AudioData file.
+    pub audio_data_filename: Option<String>,
+    pub difficulty_beatmap_sets: Vec<DifficultySet>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DifficultySet {
+    pub characteristic_name: String,
+    pub difficulty_beatmaps: Vec<DifficultyEntry>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DifficultyEntry {
+    pub difficulty: String,
+    pub difficulty_rank: i64,
+    pub beatmap_filename: String,
+    /// v4 only: lighting is stored apart from the notes.
+    pub lightshow_filename: Option<String>,
+    pub note_jump_movement_speed: f64,
+    pub note_jump_start_beat_offset: f64,
+}
+
+/// Used when Info.dat gives no (or a zero) BPM.
+const DEFAULT_BPM: f64 = 120.0;
+
+// ---------------------------------------------------------------------------
+// Raw v2 layout
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, Deserialize)]
+struct InfoV2 {
+    #[serde(rename = "_songName", default)]
+    song_name: String,
+    #[serde(rename = "_songSubName", default)]
+    song_sub_name: String,
+    #[serde(rename = "_songAuthorName", default)]
+    song_author_name: String,
+    #[serde(rename = "_levelAuthorName", default)]
+    level_author_name: String,
+    #[serde(rename = "_beatsPerMinute", default)]
+    beats_per_minute: f64,
+    #[serde(rename = "_songFilename", default)]
+    song_filename: String,
+    #[serde(rename = "_coverImageFilename", default)]
+    cover_image_filename: String,
+    #[serde(rename = "_difficultyBeatmapSets", default)]
+    difficulty_beatmap_sets: Vec<SetV2>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SetV2 {
+    #[serde(rename = "_beatmapCharacteristicName", default)]
+    characteristic_name: String,
+    #[serde(rename = "_difficultyBeatmaps", default)]
+    difficulty_beatmaps: Vec<DiffV2>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DiffV2 {
+    #[serde(rename = "_difficulty", default)]
+    difficulty: String,
+    #[serde(rename = "_difficultyRank", default)]
+    difficulty_rank: i64,
+    #[serde(rename = "_beatmapFilename", default)]
+    beatmap_filename: String,
+    #[serde(rename = "_noteJumpMovementSpeed", default)]
+    note_jump_movement_speed: f64,
+    #[serde(rename = "_noteJumpStartBeatOffset", default)]
+    note_jump_start_beat_offset: f64,
+}
+
+// ---------------------------------------------------------------------------
+// Raw v4 layout
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct InfoV4 {
+    #[serde(default)]
+    song: SongV4,
+    #[serde(default)]
+    audio: AudioV4,
+    #[serde(default)]
+    cover_image_filename: String,
+    #[serde(default)]
+    difficulty_beatmaps: Vec<DiffV4>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SongV4 {
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    sub_title: String,
+    #[serde(default)]
+    author: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AudioV4 {
+    #[serde(default)]
+    song_filename: String,
+    #[serde(default)]
+    audio_data_filename: String,
+    #[serde(default)]
+    bpm: f64,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DiffV4 {
+    #[serde(default)]
+    characteristic: String,
+    #[serde(default)]
+    difficulty: String,
+    #[serde(default)]
+    beatmap_authors: AuthorsV4,
+    #[serde(default)]
+    note_jump_movement_speed: f64,
+    #[serde(default)]
+    note_jump_start_beat_offset: f64,
+    #[serde(default)]
+    lightshow_data_filename: String,
+    #[serde(default)]
+    beatmap_data_filename: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct AuthorsV4 {
+    #[serde(default)]
+    mappers: Vec<String>,
+}
+
+// ---------------------------------------------------------------------------
+// Parsing
+// ---------------------------------------------------------------------------
+
+/// Parse Info.dat as JSON, tolerating a UTF-8 byte order mark.
+pub fn parse_json(info_dat: &str) -> Result<Value, AppError> {
+    serde_json::from_str(info_dat.trim_start_matches('\u{feff}'))
+        .map_err(|e| AppError::invalid_beatmap(format!("Info.dat is not valid JSON: {}", e)))
+}
+
+fn non_empty(value: String) -> Option<String> {
+    let trimmed = value.trim();
+    (!trimmed.is_empty()).then(|| trimmed.to_string())
+}
+
+fn bpm_or_default(bpm: f64) -> f64 {
+    if bpm.is_finite() && bpm > 0.0 {
+        bpm
+    } else {
+        DEFAULT_BPM
+    }
+}
+
+/// v4 drops `_difficultyRank`; use the values v2 maps conventionally carry.
+fn difficulty_rank(difficulty: &str) -> i64 {
+    match difficulty {
+        "Easy" => 1,
+        "Normal" => 3,
+        "Hard" => 5,
+        "Expert" => 7,
+        "ExpertPlus" => 9,
+        _ => 0,
+    }
+}
+
+/// Parse a v2 or v4 Info.dat into the normalized model.
+pub fn parse_info(info_dat: &str) -> Result<MapInfo, AppError> {
+    let json = parse_json(info_dat)?;
+    let is_v4 = json
+        .get("version")
+        .and_then(|v| v.as_str())
+        .is_some_and(|v| v.starts_with('4'));
+    let invalid = |e: serde_json::Error| AppError::invalid_beatmap(format!("Unrecognized Info.dat: {}", e));
+
+    if is_v4 {
+        let raw: InfoV4 = serde_json::from_value(json).map_err(invalid)?;
+        Ok(from_v4(raw))
+    } else if json.get("_difficultyBeatmapSets").is_some() || json.get("_version").is_some() {
+        let raw: InfoV2 = serde_json::from_value(json).map_err(invalid)?;
+        Ok(from_v2(raw))
+    } else {
+        Err(AppError::invalid_beatmap("Unrecognized Info.dat format"))
+    }
+}
+
+fn from_v2(raw: InfoV2) -> MapInfo {
+    MapInfo {
+        format: InfoFormat::V2,
+        song_name: raw.song_name,
+        song_sub_name: raw.song_sub_name,
+        song_author_name: raw.song_author_name,
+        level_author_name: raw.level_author_name,
+        bpm: bpm_or_default(raw.beats_per_minute),
+        song_filename: raw.song_filename.trim().to_string(),
+        cover_filename: non_empty(raw.cover_image_filename),
+        audio_data_filename: None,
+        difficulty_beatmap_sets: raw
+            .difficulty_beatmap_sets
+            .into_iter()
+            .map(|set| DifficultySet {
+                characteristic_name: set.characteristic_name,
+                difficulty_beatmaps: set
+                    .difficulty_beatmaps
+                    .into_iter()
+                    .map(|d| DifficultyEntry {
+                        difficulty: d.difficulty,
+                        difficulty_rank: d.difficulty_rank,
+                        beatmap_filename: d.beatmap_filename,
+                        lightshow_filename: None,
+                        note_jump_movement_speed: d.note_jump_movement_speed,
+                        note_jump_start_beat_offset: d.note_jump_start_beat_offset,
+                    })
+                    .collect(),
+            })
+            .collect(),
+    }
+}
+
+fn from_v4(raw: InfoV4) -> MapInfo {
+    let mut mappers: Vec<String> = Vec::new();
+    let mut sets: Vec<DifficultySet> = Vec::new();
+
+    for diff in raw.difficulty_beatmaps {
+        for mapper in diff.beatmap_authors.mappers {
+            if !mappers.contains(&mapper) {
+                mappers.push(mapper);
+            }
+        }
+        let entry = DifficultyEntry {
+            difficulty_rank: difficulty_rank(&diff.difficulty),
+            difficulty: diff.difficulty,
+            beatmap_filename: diff.beatmap_data_filename,
+            lightshow_filename: non_empty(diff.lightshow_data_filename),
+            note_jump_movement_speed: diff.note_jump_movement_speed,
+            note_jump_start_beat_offset: diff.note_jump_start_beat_offset,
+        };
+        // v4 lists difficulties flat; group them by characteristic in order
+        match sets.iter_mut().find(|s| s.characteristic_name == diff.characteristic) {
+            Some(set) => set.difficulty_beatmaps.push(entry),
+            None => sets.push(DifficultySet {
+                characteristic_name: diff.characteristic,
+                difficulty_beatmaps: vec![entry],
+            }),
+        }
+    }
+
+    MapInfo {
+        format: InfoFormat::V4,
+        song_name: raw.song.title,
+        song_sub_name: raw.song.sub_title,
+        song_author_name: raw.song.author,
+        level_author_name: mappers.join(", "),
+        bpm: bpm_or_default(raw.audio.bpm),
+        song_filename: raw.audio.song_filename.trim().to_string(),
+        cover_filename: non_empty(raw.cover_image_filename),
+        audio_data_filename: non_empty(raw.audio.audio_data_filename),
+        difficulty_beatmap_sets: sets,
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Files referenced by Info.dat
+// ---------------------------------------------------------------------------
+
+/// File names Info.dat refers to, as written in Info.dat.
+#[derive(Debug, Clone)]
+pub struct InfoFiles {
+    pub song: String,
+    pub cover: Option<String>,
+    /// Difficulty files in the order Info.dat lists them (may repeat).
+    pub beatmaps: Vec<String>,
+    /// Referenced but not needed for play (v4 audio data and lightshows);
+    /// skipped without a warning.
+    pub ignored: Vec<String>,
+}
+
+impl MapInfo {
+    pub fn referenced_files(&self) -> Result<InfoFiles, AppError> {
+        let song = non_empty(self.song_filename.clone()).ok_or_else(|| AppError::MissingAudio {
+            message: "Info.dat does not name a song file".to_string(),
+        })?;
+        let entries = self
+            .difficulty_beatmap_sets
+            .iter()
+            .flat_map(|set| &set.difficulty_beatmaps);
+
+        let beatmaps: Vec<String> = entries
+            .clone()
+            .filter_map(|d| non_empty(d.beatmap_filename.clone()))
+            .collect();
+        let mut ignored: Vec<String> = self
+            .audio_data_filename
+            .iter()
+            .chain(entries.filter_map(|d| d.lightshow_filename.as_ref()))
+            .filter(|name| !beatmaps.contains(name))
+            .cloned()
+            .collect();
+        ignored.sort();
+        ignored.dedup();
+
+        Ok(InfoFiles {
+            song,
+            cover: self.cover_filename.clone(),
+            beatmaps,
+            ignored,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_v2_info() {
+        let info = parse_info(
+            r#"{
+                "_version": "2.1.0",
+                "_songName": "Song",
+                "_levelAuthorName": "Mapper",
+                "_beatsPerMinute": 0,
+                "_songFilename": " song.egg ",
+                "_coverImageFilename": "",
+                "_difficultyBeatmapSets": [{
+                    "_beatmapCharacteristicName": "Standard",
+                    "_difficultyBeatmaps": [
+                        { "_difficulty": "Hard", "_difficultyRank": 5, "_beatmapFilename": "Hard.dat" }
+                    ]
+                }]
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(info.format, InfoFormat::V2);
+        assert_eq!(info.song_name, "Song");
+        assert_eq!(info.level_author_name, "Mapper");
+        assert_eq!(info.bpm, DEFAULT_BPM);
+        assert_eq!(info.song_filename, "song.egg");
+        assert!(info.cover_filename.is_none());
+        let diff = &info.difficulty_beatmap_sets[0].difficulty_beatmaps[0];
+        assert_eq!((diff.difficulty.as_str(), diff.difficulty_rank), ("Hard", 5));
+        assert_eq!(info.referenced_files().unwrap().beatmaps, ["Hard.dat"]);
+    }
+
+    #[test]
+    fn parses_v4_info() {
+        let info = parse_info(
+            "\u{feff}{
+                \"version\": \"4.0.1\",
+                \"song\": { \"title\": \"Song\", \"author\": \"Artist\" },
+                \"audio\": { \"songFilename\": \"song.ogg\", \"audioDataFilename\": \"BPMInfo.dat\", \"bpm\": 150 },
+                \"coverImageFilename\": \"cover.png\",
+                \"difficultyBeatmaps\": [
+                    { \"characteristic\": \"Standard\", \"difficulty\": \"Expert\",
+                      \"beatmapAuthors\": { \"mappers\": [\"A\", \"B\"] },
+                      \"beatmapDataFilename\": \"Expert.dat\", \"lightshowDataFilename\": \"Lights.dat\" },
+                    { \"characteristic\": \"OneSaber\", \"difficulty\": \"Easy\",
+                      \"beatmapAuthors\": { \"mappers\": [\"B\"] },
+                      \"beatmapDataFilename\": \"Easy.dat\", \"lightshowDataFilename\": \"Lights.dat\" },
+                    { \"characteristic\": \"Standard\", \"difficulty\": \"Easy\",
+                      \"beatmapDataFilename\": \"StandardEasy.dat\" }
+                ]
+            }",
+        )
+        .unwrap();
+        assert_eq!(info.format, InfoFormat::V4);
+        assert_eq!(info.song_author_name, "Artist");
+        assert_eq!(info.level_author_name, "A, B");
+        assert_eq!(info.bpm, 150.0);
+        assert_eq!(info.cover_filename.as_deref(), Some("cover.png"));
+        let sets: Vec<_> = info
+            .difficulty_beatmap_sets
+            .iter()
+            .map(|s| (s.characteristic_name.as_str(), s.difficulty_beatmaps.len()))
+            .collect();
+        assert_eq!(sets, [("Standard", 2), ("OneSaber", 1)]);
+        assert_eq!(info.difficulty_beatmap_sets[0].difficulty_beatmaps[0].difficulty_rank, 7);
+
+        let files = info.referenced_files().unwrap();
+        assert_eq!(files.beatmaps, ["Expert.dat", "StandardEasy.dat", "Easy.dat"]);
+        assert_eq!(files.ignored, ["BPMInfo.dat", "Lights.dat"]);
+    }
+
+    #[test]
+    fn rejects_unknown_info() {
+        for info in ["not json", r#"{"songName": "x"}"#, r#"{"_version": "2", "_songName": 5}"#] {
+            assert!(matches!(parse_info(info), Err(AppError::InvalidBeatmap { .. })), "{}", info);
+        }
+    }
+}
@@ -0,0 +1,322 @@
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use tauri::http::{header, Method, Request, Response, StatusCode, Uri};
+use tauri::{AppHandle, UriSchemeContext, UriSchemeResponder};
+
+use crate::audio;
+use crate::error::AppError;
+use crate::map_cache;
+
+// ---------------------------------------------------------------------------
+// `moontapper://` protocol serving files from the map download cache
+// ---------------------------------------------------------------------------
+//
+// moontapper://map/<id>/audio
+// moontapper://map/<id>/audio.wav    the audio transcoded to WAV on first use
+// moontapper://map/<id>/cover
+// moontapper://map/<id>/info
+// moontapper://map/<id>/diff/<filename>
+//
+// On Windows and Android the webview reaches custom schemes through
+// `http://moontapper.localhost/...`, and `convertFileSrc` percent-encodes the
+// whole path, so both `map` as host and `map` as first path segment are accepted.
+
+pub const SCHEME: &str = "moontapper";
+
+enum MapFile {
+    Audio,
+    TranscodedAudio,
+    Cover,
+    Info,
+    Diff(String),
+}
+
+/// Handler for `register_asynchronous_uri_scheme_protocol`. File reads run on
+/// the blocking pool so the webview thread is never held up.
+pub fn handle(
+    ctx: UriSchemeContext<'_, tauri::Wry>,
+    request: Request<Vec<u8>>,
+    responder: UriSchemeResponder,
+) {
+    let app_handle = ctx.app_handle().clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        responder.respond(respond(&app_handle, &request));
+    });
+}
+
+fn respond(app_handle: &AppHandle, request: &Request<Vec<u8>>) -> Response<Vec<u8>> {
+    if request.method() == Method::OPTIONS {
+        return base_response(StatusCode::NO_CONTENT)
+            .header(header::ACCESS_CONTROL_ALLOW_METHODS, "GET, HEAD, OPTIONS")
+            .header(header::ACCESS_CONTROL_ALLOW_HEADERS, "Range")
+            .body(Vec::new())
+            .unwrap_or_default();
+    }
+    if request.method() != Method::GET && request.method() != Method::HEAD {
+        return text_response(StatusCode::METHOD_NOT_ALLOWED, "Method not allowed");
+    }
+
+    let Some((map_id, file)) = parse_route(request.uri()) else {
+        return text_response(StatusCode::NOT_FOUND, "Unknown map resource");
+    };
+    let path = match resolve_path(app_handle, &map_id, file) {
+        Ok(Some(path)) => path,
+        Ok(None) => return text_response(StatusCode::NOT_FOUND, "Map file not found"),
+        Err(e) => return text_response(StatusCode::INTERNAL_SERVER_ERROR, e.message()),
+    };
+
+    let range = request
+        .headers()
+        .get(header::RANGE)
+        .and_then(|value| value.to_str().ok());
+    match serve_file(&path, range, request.method() == Method::HEAD) {
+        Ok(response) => response,
+        Err(e) => text_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            &format!("Failed to read map file: {}", e),
+        ),
+    }
+}
+
+fn parse_route(uri: &Uri) -> Option<(String, MapFile)> {
+    let path = urlencoding::decode(uri.path()).ok()?;
+    let mut segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+    if uri.host() != Some("map") {
+        if segments.first() != Some(&"map") {
+            return None;
+        }
+        segments.remove(0);
+    }
+
+    let (map_id, file) = match segments.as_slice() {
+        [id, "audio"] => (id, MapFile::Audio),
+        [id, "audio.wav"] => (id, MapFile::TranscodedAudio),
+        [id, "cover"] => (id, MapFile::Cover),
+        [id, "info"] => (id, MapFile::Info),
+        [id, "diff", name] => (id, MapFile::Diff(map_cache::safe_filename(name)?)),
+        _ => return None,
+    };
+    // Reject ids that would step outside the downloads directory
+    if map_cache::safe_filename(map_id).as_deref() != Some(*map_id) {
+        return None;
+    }
+    Some((map_id.to_string(), file))
+}
+
+fn resolve_path(
+    app_handle: &AppHandle,
+    map_id: &str,
+    file: MapFile,
+) -> Result<Option<PathBuf>, AppError> {
+    let Some(manifest) = map_cache::read_manifest(app_handle, map_id)? else {
+        return Ok(None);
+    };
+    let filename = match file {
+        MapFile::Audio => manifest.audio_filename,
+        MapFile::TranscodedAudio => {
+            return match audio::transcoded_map_audio(app_handle, map_id) {
+                Ok(path) => Ok(Some(path)),
+                Err(AppError::NotFound { .. }) => Ok(None),
+                Err(e) => Err(e),
+            };
+        }
+        MapFile::Cover => match manifest.cover_filename {
+            Some(name) => name,
+            None => return Ok(None),
+        },
+        MapFile::Info => map_cache::INFO_FILENAME.to_string(),
+        MapFile::Diff(name) => match manifest
+            .beatmap_filenames
+            .into_iter()
+            .find(|f| f.eq_ignore_ascii_case(&name))
+        {
+            Some(name) => name,
+            None => return Ok(None),
+        },
+    };
+    let path = map_cache::map_dir(app_handle, map_id)?.join(filename);
+    Ok(path.exists().then_some(path))
+}
+
+// ---------------------------------------------------------------------------
+// Responses
+// ---------------------------------------------------------------------------
+
+fn base_response(status: StatusCode) -> tauri::http::response::Builder {
+    Response::builder()
+        .status(status)
+        .header(header::ACCESS_CONTROL_ALLOW_ORIGIN, "*")
+}
+
+fn text_response(status: StatusCode, message: &str) -> Response<Vec<u8>> {
+    base_response(status)
+        .header(header::CONTENT_TYPE, "text/plain; charset=utf-8")
+        .body(message.as_bytes().to_vec())
+        .unwrap_or_default()
+}
+
+fn mime_type(path: &Path) -> &'static str {
+    let ext = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase())
+        .unwrap_or_default();
+    match ext.as_str() {
+        "egg" | "ogg" => "audio/ogg",
+        "mp3" => "audio/mpeg",
+        "wav" => "audio/wav",
+        "flac" => "audio/flac",
+        "jpg" | "jpeg" => "image/jpeg",
+        "png" => "image/png",
+        "dat" | "json" => "application/json",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Parse a `Range: bytes=...` header into an inclusive byte range. Only the
+/// first range of a multi-range request is honoured. `None` means the range
+/// can't be satisfied.
+fn parse_range(value: &str, len: u64) -> Option<(u64, u64)> {
+    let spec = value.trim().strip_prefix("bytes=")?.split(',').next()?.trim();
+    let (start, end) = spec.split_once('-')?;
+    let (start, end) = match (start.trim(), end.trim()) {
+        ("", suffix) => {
+            let suffix: u64 = suffix.parse().ok()?;
+            (len.saturating_sub(suffix), len.checked_sub(1)?)
+        }
+        (start, "") => (start.parse().ok()?, len.checked_sub(1)?),
+        (start, end) => (start.parse().ok()?, end.parse::<u64>().ok()?.min(len.checked_sub(1)?)),
+    };
+    (start <= end && start < len).then_some((start, end))
+}
+
+fn serve_file(path: &Path, range: Option<&str>, head_only: bool) -> std::io::Result<Response<Vec<u8>>> {
+    let mut file = File::open(path)?;
+    let len = file.metadata()?.len();
+
+    let builder = base_response(StatusCode::OK)
+        .header(header::CONTENT_TYPE, mime_type(path))
+        .header(header::ACCEPT_RANGES, "bytes");
+
+    let (builder, start, count) = match range {
+        None => (builder, 0, len),
+        Some(value) => match parse_range(value, len) {
+            Some((start, end)) => (
+                builder
+                    .status(StatusCode::PARTIAL_CONTENT)
+                    .header(header::CONTENT_RANGE, format!("bytes {}-{}/{}", start, end, len)),
+                start,
+                end - start + 1,
+            ),
+            None => {
+                return Ok(builder
+                    .status(StatusCode::RANGE_NOT_SATISFIABLE)
+                    .header(header::CONTENT_RANGE, format!("bytes */{}", len))
+                    .body(Vec::new())
+                    .unwrap_or_default())
+            }
+        },
+    };
+
+    let mut body = Vec::new();
+    if !head_only {
+        file.seek(SeekFrom::Start(start))?;
+        file.take(count).read_to_end(&mut body)?;
+    }
+
+    Ok(builder
+        .header(header::CONTENT_LENGTH, count)
+        .body(body)
+        .unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn route(uri: &str) -> Option<(String, &'static str)> {
+        let (id, file) = parse_route(&uri.parse::<Uri>().unwrap())?;
+        let name = match file {
+            MapFile::Audio => "audio",
+            MapFile::TranscodedAudio => "audio.wav",
+            MapFile::Cover => "cover",
+            MapFile::Info => "info",
+            MapFile::Diff(_) => "diff",
+        };
+        Some((id, name))
+    }
+
+    #[test]
+    fn range_forms() {
+        assert_eq!(parse_range("bytes=0-99", 1000), Some((0, 99)));
+        // Open-ended and suffix ranges
+        assert_eq!(parse_range("bytes=900-", 1000), Some((900, 999)));
+        assert_eq!(parse_range("bytes=-100", 1000), Some((900, 999)));
+        // A suffix longer than the file covers all of it
+        assert_eq!(parse_range("bytes=-5000", 1000), Some((0, 999)));
+        // The end is clamped to the file
+        assert_eq!(parse_range("bytes=500-5000", 1000), Some((500, 999)));
+        // Only the first of several ranges is served
+        assert_eq!(parse_range("bytes=0-9, 20-29", 1000), Some((0, 9)));
+        assert_eq!(parse_range(" bytes= 10 - 19 ", 1000), Some((10, 19)));
+    }
+
+    #[test]
+    fn unsatisfiable_ranges() {
+        assert_eq!(parse_range("bytes=1000-", 1000), None);
+        assert_eq!(parse_range("bytes=10-5", 1000), None);
+        assert_eq!(parse_range("bytes=0-0", 0), None);
+        assert_eq!(parse_range("bytes=-0", 1000), None);
+        assert_eq!(parse_range("bytes=-", 1000), None);
+        assert_eq!(parse_range("items=0-1", 1000), None);
+        assert_eq!(parse_range("bytes=a-b", 1000), None);
+    }
+
+    #[test]
+    fn routes_with_map_as_host_or_first_segment() {
+        assert_eq!(route("moontapper://map/1a2b/audio"), Some(("1a2b".into(), "audio")));
+        assert_eq!(route("moontapper://map/1a2b/audio.wav"), Some(("1a2b".into(), "audio.wav")));
+        assert_eq!(route("http://moontapper.localhost/map/1a2b/cover"), Some(("1a2b".into(), "cover")));
+        assert_eq!(
+            route("http://moontapper.localhost/map%2F1a2b%2Fdiff%2FExpert.dat"),
+            Some(("1a2b".into(), "diff"))
+        );
+        assert_eq!(route("moontapper://map/1a2b/other"), None);
+        assert_eq!(route("http://moontapper.localhost/1a2b/info"), None);
+    }
+
+    #[test]
+    fn routes_reject_unsafe_ids_and_names() {
+        assert_eq!(route("moontapper://map/..%2F..%2Fetc/info"), None);
+        assert_eq!(route("moontapper://map/../info"), None);
+        assert_eq!(route("moontapper://map/1a2b/diff/.."), None);
+    }
+
+    #[test]
+    fn serves_partial_content() {
+        let path = std::env::temp_dir().join(format!("moontapper-range-{}.ogg", std::process::id()));
+        std::fs::write(&path, b"0123456789").unwrap();
+
+        let full = serve_file(&path, None, false).unwrap();
+        assert_eq!(full.status(), StatusCode::OK);
+        assert_eq!(full.body().as_slice(), b"0123456789");
+        assert_eq!(full.headers()[header::CONTENT_TYPE], "audio/ogg");
+
+        let partial = serve_file(&path, Some("bytes=2-4"), false).unwrap();
+        assert_eq!(partial.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(partial.body().as_slice(), b"234");
+        assert_eq!(partial.headers()[header::CONTENT_RANGE], "bytes 2-4/10");
+
+        let head = serve_file(&path, Some("bytes=-3"), true).unwrap();
+        assert!(head.body().is_empty());
+        assert_eq!(head.headers()[header::CONTENT_LENGTH], "3");
+
+        let invalid = serve_file(&path, Some("bytes=20-"), false).unwrap();
+        assert_eq!(invalid.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+        assert_eq!(invalid.headers()[header::CONTENT_RANGE], "bytes */10");
+
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,141 @@
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::de::DeserializeOwned;
+
+use crate::beatsaver::{BeatSaverMap, BeatSaverSearchResponse};
+use crate::error::AppError;
+
+// ---------------------------------------------------------------------------
+// On-disk BeatSaver metadata cache (per map id and per query key)
+// ---------------------------------------------------------------------------
+
+pub struct CachedEntry<T> {
+    pub value: T,
+    pub fetched_at: i64,
+}
+
+impl<T> CachedEntry<T> {
+    pub fn is_fresh(&self, ttl_secs: u64) -> bool {
+        now_secs() - self.fetched_at < ttl_secs as i64
+    }
+}
+
+pub fn now_secs() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or_default()
+}
+
+/// Rows that no longer deserialize (e.g. after a schema change upstream) are
+/// treated as cache misses rather than errors.
+fn decode<T: DeserializeOwned>(row: Option<(String, i64)>) -> Option<CachedEntry<T>> {
+    let (json, fetched_at) = row?;
+    let value = serde_json::from_str(&json).ok()?;
+    Some(CachedEntry { value, fetched_at })
+}
+
+// ---------------------------------------------------------------------------
+// Per-map entries
+// ---------------------------------------------------------------------------
+
+pub fn get_map(conn: &Connection, id: &str) -> Result<Option<CachedEntry<BeatSaverMap>>, AppError> {
+    let row = conn
+        .query_row(
+            "SELECT json, fetched_at FROM map_metadata WHERE id = ?1",
+            params![id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()?;
+    Ok(decode(row))
+}
+
+pub fn put_map(conn: &Connection, map: &BeatSaverMap) -> Result<(), AppError> {
+    let json = serde_json::to_string(map)
+        .map_err(|e| AppError::io(format!("Failed to serialize map metadata: {}", e)))?;
+    conn.execute(
+        "INSERT OR REPLACE INTO map_metadata
+            (id, name, song_name, song_author_name, level_author_name, json, fetched_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![
+            map.id,
+            map.name,
+            map.metadata.song_name,
+            map.metadata.song_author_name,
+            map.metadata.level_author_name,
+            json,
+            now_secs(),
+        ],
+    )?;
+    Ok(())
+}
+
+fn query_maps(conn: &Connection, sql: &str, params: impl rusqlite::Params) -> Result<Vec<BeatSaverMap>, AppError> {
+    let mut stmt = conn.prepare(sql)?;
+    let rows = stmt
+        .query_map(params, |row| row.get::<_, String>(0))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(rows
+        .iter()
+        .filter_map(|json| serde_json::from_str(json).ok())
+        .collect())
+}
+
+/// Case-insensitive substring search over cached map names and authors.
+pub fn search_maps(conn: &Connection, query: &str, limit: i64) -> Result<Vec<BeatSaverMap>, AppError> {
+    let pattern = format!("%{}%", query.trim().replace('%', "\\%").replace('_', "\\_"));
+    query_maps(
+        conn,
+        "SELECT json FROM map_metadata
+         WHERE name LIKE ?1 ESCAPE '\\' OR song_name LIKE ?1 ESCAPE '\\'
+            OR song_author_name LIKE ?1 ESCAPE '\\' OR level_author_name LIKE ?1 ESCAPE '\\'
+         ORDER BY fetched_at DESC LIMIT ?2",
+        params![pattern, limit],
+    )
+}
+
+/// Cached metadata for the given map ids, in the order given.
+pub fn maps_by_ids(conn: &Connection, ids: &[String]) -> Result<Vec<BeatSaverMap>, AppError> {
+    let mut maps = Vec::with_capacity(ids.len());
+    for id in ids {
+        if let Some(entry) = get_map(conn, id)? {
+            maps.push(entry.value);
+        }
+    }
+    Ok(maps)
+}
+
+// ---------------------------------------------------------------------------
+// Per-query entries (search and browse responses keyed by request path)
+// ---------------------------------------------------------------------------
+
+pub fn get_query(
+    conn: &Connection,
+    key: &str,
+) -> Result<Option<CachedEntry<BeatSaverSearchResponse>>, AppError> {
+    let row = conn
+        .query_row(
+            "SELECT json, fetched_at FROM query_cache WHERE key = ?1",
+            params![key],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()?;
+    Ok(decode(row))
+}
+
+/// Store a search/browse response and refresh every map it contains.
+pub fn put_query(
+    conn: &Connection,
+    key: &str,
+    response: &BeatSaverSearchResponse,
+) -> Result<(), AppError> {
+    let json = serde_json::to_string(response)
+        .map_err(|e| AppError::io(format!("Failed to serialize search response: {}", e)))?;
+    conn.execute(
+        "INSERT OR REPLACE INTO query_cache (key, json, fetched_at) VALUES (?1, ?2, ?3)",
+        params![key, json, now_secs()],
+    )?;
+    for map in &response.docs {
+        put_map(conn, map)?;
+    }
+    Ok(())
+}
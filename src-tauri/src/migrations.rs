@@ -0,0 +1,272 @@
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+
+use crate::db::Database;
+use crate::error::AppError;
+
+// ---------------------------------------------------------------------------
+// Embedded schema migrations
+// ---------------------------------------------------------------------------
+
+pub struct Migration {
+    pub version: i64,
+    pub name: &'static str,
+    pub sql: &'static str,
+}
+
+/// Ordered list of schema migrations. Append new entries at the end with the
+/// next version number; never edit or reorder a migration once it has shipped.
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "create_scores",
+        sql: "CREATE TABLE scores (
+                id TEXT PRIMARY KEY,
+                map_id TEXT NOT NULL,
+                map_name TEXT NOT NULL,
+                difficulty TEXT NOT NULL,
+                lane_mode INTEGER NOT NULL,
+                score INTEGER NOT NULL,
+                max_combo INTEGER NOT NULL,
+                perfect INTEGER NOT NULL,
+                good INTEGER NOT NULL,
+                miss INTEGER NOT NULL,
+                grade TEXT NOT NULL,
+                date TEXT NOT NULL
+            );
+            CREATE INDEX idx_scores_chart ON scores (map_id, difficulty, lane_mode, score DESC);
+            CREATE INDEX idx_scores_date ON scores (date DESC);",
+    },
+    Migration {
+        version: 2,
+        name: "create_playlists",
+        sql: "CREATE TABLE playlists (
+                id TEXT PRIMARY KEY,
+                name TEXT NOT NULL,
+                position INTEGER NOT NULL,
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL
+            );
+            CREATE TABLE playlist_tracks (
+                playlist_id TEXT NOT NULL REFERENCES playlists (id) ON DELETE CASCADE,
+                track_id TEXT NOT NULL,
+                position INTEGER NOT NULL,
+                song_name TEXT NOT NULL,
+                song_author_name TEXT NOT NULL,
+                level_author_name TEXT NOT NULL,
+                bpm REAL NOT NULL,
+                duration REAL NOT NULL,
+                cover_url TEXT NOT NULL,
+                diffs TEXT NOT NULL,
+                added_at TEXT NOT NULL,
+                PRIMARY KEY (playlist_id, track_id)
+            );
+            CREATE TABLE app_meta (
+                key TEXT PRIMARY KEY,
+                value TEXT NOT NULL
+            );
+            INSERT INTO playlists (id, name, position, created_at, updated_at)
+            VALUES (
+                'favorites', 'Favorites', 0,
+                strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), strftime('%Y-%m-%dT%H:%M:%fZ', 'now')
+            );",
+    },
+    Migration {
+        version: 3,
+        name: "create_metadata_cache",
+        sql: "CREATE TABLE map_metadata (
+                id TEXT PRIMARY KEY,
+                name TEXT NOT NULL,
+                song_name TEXT NOT NULL,
+                song_author_name TEXT NOT NULL,
+                level_author_name TEXT NOT NULL,
+                json TEXT NOT NULL,
+                fetched_at INTEGER NOT NULL
+            );
+            CREATE TABLE query_cache (
+                key TEXT PRIMARY KEY,
+                json TEXT NOT NULL,
+                fetched_at INTEGER NOT NULL
+            );",
+    },
+    Migration {
+        version: 4,
+        name: "create_map_usage",
+        sql: "CREATE TABLE map_usage (
+                map_id TEXT PRIMARY KEY,
+                last_played_at INTEGER NOT NULL
+            );",
+    },
+];
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SchemaVersion {
+    pub current: i64,
+    pub latest: i64,
+}
+
+fn latest_version() -> i64 {
+    MIGRATIONS.last().map(|m| m.version).unwrap_or(0)
+}
+
+fn ensure_version_table(conn: &Connection) -> Result<(), AppError> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS schema_version (
+            version INTEGER PRIMARY KEY,
+            name TEXT NOT NULL,
+            applied_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+        );",
+    )
+    .map_err(|e| AppError::io(format!("Failed to create schema_version table: {}", e)))
+}
+
+fn current_version(conn: &Connection) -> Result<i64, AppError> {
+    conn.query_row("SELECT MAX(version) FROM schema_version", [], |row| {
+        row.get::<_, Option<i64>>(0)
+    })
+    .optional()
+    .map(|v| v.flatten().unwrap_or(0))
+    .map_err(|e| AppError::io(format!("Failed to read schema version: {}", e)))
+}
+
+fn migration_error(migration: &Migration, what: &str, e: rusqlite::Error) -> AppError {
+    AppError::io(format!("Migration {} ({}) {}: {}", migration.version, migration.name, what, e))
+}
+
+/// Apply every migration newer than the database's recorded version, each in
+/// its own transaction.
+pub fn apply_migrations(conn: &mut Connection) -> Result<i64, AppError> {
+    ensure_version_table(conn)?;
+
+    let current = current_version(conn)?;
+    let latest = latest_version();
+    if current > latest {
+        return Err(AppError::internal(format!(
+            "Database schema version {} is newer than this build supports ({})",
+            current, latest
+        )));
+    }
+
+    for migration in MIGRATIONS.iter().filter(|m| m.version > current) {
+        let tx = conn
+            .transaction()
+            .map_err(|e| migration_error(migration, "failed to start", e))?;
+        tx.execute_batch(migration.sql)
+            .map_err(|e| migration_error(migration, "failed", e))?;
+        tx.execute(
+            "INSERT INTO schema_version (version, name) VALUES (?1, ?2)",
+            params![migration.version, migration.name],
+        )
+        .map_err(|e| migration_error(migration, "failed to record", e))?;
+        tx.commit()
+            .map_err(|e| migration_error(migration, "failed to commit", e))?;
+    }
+
+    Ok(latest)
+}
+
+// ---------------------------------------------------------------------------
+// Tauri commands
+// ---------------------------------------------------------------------------
+
+/// Report the database's applied schema version and the latest known version.
+#[tauri::command]
+pub fn db_schema_version(db: tauri::State<'_, Database>) -> Result<SchemaVersion, AppError> {
+    let conn = db.lock()?;
+    Ok(SchemaVersion {
+        current: current_version(&conn)?,
+        latest: latest_version(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn applied_versions(conn: &Connection) -> Vec<i64> {
+        let mut stmt = conn.prepare("SELECT version FROM schema_version ORDER BY version").unwrap();
+        stmt.query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap()
+    }
+
+    fn table_exists(conn: &Connection, name: &str) -> bool {
+        conn.query_row(
+            "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = ?1",
+            params![name],
+            |row| row.get::<_, i64>(0),
+        )
+        .unwrap()
+            == 1
+    }
+
+    #[test]
+    fn versions_are_consecutive_from_one() {
+        for (i, migration) in MIGRATIONS.iter().enumerate() {
+            assert_eq!(migration.version, i as i64 + 1, "migration {}", migration.name);
+        }
+    }
+
+    #[test]
+    fn fresh_database_gets_every_migration_in_order() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        assert_eq!(apply_migrations(&mut conn).unwrap(), latest_version());
+        assert_eq!(applied_versions(&conn), (1..=latest_version()).collect::<Vec<_>>());
+        for table in ["scores", "playlists", "playlist_tracks", "map_metadata", "map_usage"] {
+            assert!(table_exists(&conn, table), "missing table {}", table);
+        }
+    }
+
+    #[test]
+    fn applying_twice_is_a_no_op() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        apply_migrations(&mut conn).unwrap();
+        apply_migrations(&mut conn).unwrap();
+        assert_eq!(applied_versions(&conn).len(), MIGRATIONS.len());
+        let favorites: i64 = conn
+            .query_row("SELECT COUNT(*) FROM playlists WHERE id = 'favorites'", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(favorites, 1);
+    }
+
+    #[test]
+    fn only_newer_migrations_run_on_an_older_database() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        ensure_version_table(&conn).unwrap();
+        conn.execute_batch(MIGRATIONS[0].sql).unwrap();
+        conn.execute(
+            "INSERT INTO schema_version (version, name) VALUES (?1, ?2)",
+            params![MIGRATIONS[0].version, MIGRATIONS[0].name],
+        )
+        .unwrap();
+
+        // Re-running migration 1 would fail on the existing scores table
+        apply_migrations(&mut conn).unwrap();
+        assert_eq!(applied_versions(&conn), (1..=latest_version()).collect::<Vec<_>>());
+        assert!(table_exists(&conn, "map_usage"));
+    }
+
+    #[test]
+    fn newer_database_is_rejected() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        apply_migrations(&mut conn).unwrap();
+        conn.execute(
+            "INSERT INTO schema_version (version, name) VALUES (?1, 'from_the_future')",
+            params![latest_version() + 1],
+        )
+        .unwrap();
+        assert!(apply_migrations(&mut conn).is_err());
+    }
+
+    #[test]
+    fn failed_migration_leaves_no_partial_version() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        // A clashing table makes migration 1 fail part-way
+        conn.execute_batch("CREATE TABLE scores (id TEXT)").unwrap();
+        assert!(apply_migrations(&mut conn).is_err());
+        assert!(applied_versions(&conn).is_empty());
+        assert!(!table_exists(&conn, "playlists"));
+    }
+}
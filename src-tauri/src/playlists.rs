@@ -0,0 +1,533 @@
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+
+use crate::db::Database;
+use crate::error::AppError;
+
+// ---------------------------------------------------------------------------
+// Playlist types (mirrors TypeScript RhythmPlaylist / PlaylistTrack)
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlaylistTrackDiff {
+    pub difficulty: String,
+    pub characteristic: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlaylistTrack {
+    pub id: String,
+    #[serde(default)]
+    pub song_name: String,
+    #[serde(default)]
+    pub song_author_name: String,
+    #[serde(default)]
+    pub level_author_name: String,
+    #[serde(default)]
+    pub bpm: f64,
+    #[serde(default)]
+    pub duration: f64,
+    #[serde(rename = "coverURL", default)]
+    pub cover_url: String,
+    #[serde(default)]
+    pub diffs: Vec<PlaylistTrackDiff>,
+    #[serde(default)]
+    pub added_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RhythmPlaylist {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub tracks: Vec<PlaylistTrack>,
+    #[serde(default)]
+    pub created_at: String,
+    #[serde(default)]
+    pub updated_at: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlaylistImportResult {
+    pub already_imported: bool,
+    pub playlists: usize,
+    pub tracks: usize,
+}
+
+// ---------------------------------------------------------------------------
+// Constants
+// ---------------------------------------------------------------------------
+
+const FAVORITES_PLAYLIST_ID: &str = "favorites";
+const IMPORT_MARKER_KEY: &str = "playlists_imported_from_local_storage";
+const NOW_SQL: &str = "strftime('%Y-%m-%dT%H:%M:%fZ', 'now')";
+
+// ---------------------------------------------------------------------------
+// Row mapping and queries
+// ---------------------------------------------------------------------------
+
+fn not_found(id: &str) -> AppError {
+    AppError::NotFound {
+        message: format!("Playlist {} not found", id),
+    }
+}
+
+fn row_to_track(row: &Row<'_>) -> rusqlite::Result<PlaylistTrack> {
+    let diffs_json: String = row.get(7)?;
+    Ok(PlaylistTrack {
+        id: row.get(0)?,
+        song_name: row.get(1)?,
+        song_author_name: row.get(2)?,
+        level_author_name: row.get(3)?,
+        bpm: row.get(4)?,
+        duration: row.get(5)?,
+        cover_url: row.get(6)?,
+        diffs: serde_json::from_str(&diffs_json).unwrap_or_default(),
+        added_at: row.get(8)?,
+    })
+}
+
+fn load_tracks(conn: &Connection, playlist_id: &str) -> Result<Vec<PlaylistTrack>, AppError> {
+    let mut stmt = conn.prepare(
+        "SELECT track_id, song_name, song_author_name, level_author_name, bpm, duration,
+                cover_url, diffs, added_at
+         FROM playlist_tracks WHERE playlist_id = ?1 ORDER BY position",
+    )?;
+    let tracks = stmt
+        .query_map(params![playlist_id], row_to_track)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(tracks)
+}
+
+fn load_playlist(conn: &Connection, id: &str) -> Result<RhythmPlaylist, AppError> {
+    let playlist = conn
+        .query_row(
+            "SELECT id, name, created_at, updated_at FROM playlists WHERE id = ?1",
+            params![id],
+            |row| {
+                Ok(RhythmPlaylist {
+                    id: row.get(0)?,
+                    name: row.get(1)?,
+                    tracks: Vec::new(),
+                    created_at: row.get(2)?,
+                    updated_at: row.get(3)?,
+                })
+            },
+        )
+        .optional()?
+        .ok_or_else(|| not_found(id))?;
+
+    Ok(RhythmPlaylist {
+        tracks: load_tracks(conn, id)?,
+        ..playlist
+    })
+}
+
+fn touch_playlist(conn: &Connection, id: &str) -> Result<(), AppError> {
+    let updated = conn.execute(
+        &format!("UPDATE playlists SET updated_at = {} WHERE id = ?1", NOW_SQL),
+        params![id],
+    )?;
+    if updated == 0 {
+        return Err(not_found(id));
+    }
+    Ok(())
+}
+
+fn insert_track(conn: &Connection, playlist_id: &str, track: &PlaylistTrack) -> Result<bool, AppError> {
+    let diffs = serde_json::to_string(&track.diffs)
+        .map_err(|e| AppError::internal(format!("Failed to serialize track difficulties: {}", e)))?;
+    let added_at = if track.added_at.is_empty() {
+        None
+    } else {
+        Some(track.added_at.as_str())
+    };
+    let inserted = conn
+        .execute(
+            &format!(
+                "INSERT OR IGNORE INTO playlist_tracks
+                    (playlist_id, track_id, position, song_name, song_author_name, level_author_name,
+                     bpm, duration, cover_url, diffs, added_at)
+                 VALUES (?1, ?2,
+                    (SELECT COALESCE(MAX(position) + 1, 0) FROM playlist_tracks WHERE playlist_id = ?1),
+                    ?3, ?4, ?5, ?6, ?7, ?8, ?9, COALESCE(?10, {}))",
+                NOW_SQL
+            ),
+            params![
+                playlist_id,
+                track.id,
+                track.song_name,
+                track.song_author_name,
+                track.level_author_name,
+                track.bpm,
+                track.duration,
+                track.cover_url,
+                diffs,
+                added_at,
+            ],
+        )?;
+    Ok(inserted > 0)
+}
+
+/// Trimmed playlist name, rejecting empty and whitespace-only names.
+fn check_name(name: &str) -> Result<&str, AppError> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(AppError::invalid_argument("Playlist name cannot be empty"));
+    }
+    Ok(name)
+}
+
+fn add_track(
+    conn: &mut Connection,
+    playlist_id: &str,
+    track: &PlaylistTrack,
+) -> Result<RhythmPlaylist, AppError> {
+    let tx = conn.transaction()?;
+    touch_playlist(&tx, playlist_id)?;
+    insert_track(&tx, playlist_id, track)?;
+    tx.commit()?;
+    load_playlist(conn, playlist_id)
+}
+
+fn remove_track(
+    conn: &mut Connection,
+    playlist_id: &str,
+    track_id: &str,
+) -> Result<RhythmPlaylist, AppError> {
+    let tx = conn.transaction()?;
+    touch_playlist(&tx, playlist_id)?;
+    tx.execute(
+        "DELETE FROM playlist_tracks WHERE playlist_id = ?1 AND track_id = ?2",
+        params![playlist_id, track_id],
+    )?;
+    tx.commit()?;
+    load_playlist(conn, playlist_id)
+}
+
+fn reorder_tracks(
+    conn: &mut Connection,
+    playlist_id: &str,
+    track_ids: &[String],
+) -> Result<RhythmPlaylist, AppError> {
+    let tx = conn.transaction()?;
+    touch_playlist(&tx, playlist_id)?;
+    for (position, track_id) in track_ids.iter().enumerate() {
+        tx.execute(
+            "UPDATE playlist_tracks SET position = ?3 WHERE playlist_id = ?1 AND track_id = ?2",
+            params![playlist_id, track_id, position as i64],
+        )?;
+    }
+    tx.commit()?;
+    load_playlist(conn, playlist_id)
+}
+
+/// Import playlists once; the `app_meta` marker makes later calls no-ops.
+fn import_playlists(
+    conn: &mut Connection,
+    playlists: &[RhythmPlaylist],
+) -> Result<PlaylistImportResult, AppError> {
+    let tx = conn.transaction()?;
+
+    let already_imported = tx
+        .query_row(
+            "SELECT 1 FROM app_meta WHERE key = ?1",
+            params![IMPORT_MARKER_KEY],
+            |_| Ok(()),
+        )
+        .optional()?
+        .is_some();
+    if already_imported {
+        return Ok(PlaylistImportResult {
+            already_imported: true,
+            playlists: 0,
+            tracks: 0,
+        });
+    }
+
+    let mut imported_playlists = 0;
+    let mut imported_tracks = 0;
+    for playlist in playlists {
+        // Favorites already exists (seeded by migration); merge its tracks
+        let created = tx
+            .execute(
+                &format!(
+                    "INSERT OR IGNORE INTO playlists (id, name, position, created_at, updated_at)
+                     VALUES (?1, ?2,
+                        (SELECT COALESCE(MAX(position) + 1, 0) FROM playlists),
+                        COALESCE(NULLIF(?3, ''), {now}), COALESCE(NULLIF(?4, ''), {now}))",
+                    now = NOW_SQL
+                ),
+                params![playlist.id, playlist.name, playlist.created_at, playlist.updated_at],
+            )?;
+        imported_playlists += created;
+
+        for track in &playlist.tracks {
+            if insert_track(&tx, &playlist.id, track)? {
+                imported_tracks += 1;
+            }
+        }
+    }
+
+    tx.execute(
+        &format!("INSERT INTO app_meta (key, value) VALUES (?1, {})", NOW_SQL),
+        params![IMPORT_MARKER_KEY],
+    )?;
+    tx.commit()?;
+
+    Ok(PlaylistImportResult {
+        already_imported: false,
+        playlists: imported_playlists,
+        tracks: imported_tracks,
+    })
+}
+
+// ---------------------------------------------------------------------------
+// Tauri commands
+// ---------------------------------------------------------------------------
+
+/// All playlists with their tracks, in display order.
+#[tauri::command]
+pub fn playlists_list(db: tauri::State<'_, Database>) -> Result<Vec<RhythmPlaylist>, AppError> {
+    let conn = db.lock()?;
+    let mut stmt = conn.prepare("SELECT id FROM playlists ORDER BY position, created_at")?;
+    let ids = stmt
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    ids.iter().map(|id| load_playlist(&conn, id)).collect()
+}
+
+/// Create an empty playlist at the end of the list.
+#[tauri::command]
+pub fn playlists_create(
+    name: String,
+    db: tauri::State<'_, Database>,
+) -> Result<RhythmPlaylist, AppError> {
+    let name = check_name(&name)?;
+    let conn = db.lock()?;
+    let id: String = conn
+        .query_row(
+            &format!(
+                "INSERT INTO playlists (id, name, position, created_at, updated_at)
+                 VALUES (
+                    lower(hex(randomblob(16))), ?1,
+                    (SELECT COALESCE(MAX(position) + 1, 0) FROM playlists),
+                    {now}, {now}
+                 )
+                 RETURNING id",
+                now = NOW_SQL
+            ),
+            params![name],
+            |row| row.get(0),
+        )?;
+    load_playlist(&conn, &id)
+}
+
+/// Rename a playlist.
+#[tauri::command]
+pub fn playlists_rename(
+    id: String,
+    name: String,
+    db: tauri::State<'_, Database>,
+) -> Result<RhythmPlaylist, AppError> {
+    let name = check_name(&name)?;
+    let conn = db.lock()?;
+    conn.execute(
+        "UPDATE playlists SET name = ?2 WHERE id = ?1",
+        params![id, name],
+    )?;
+    touch_playlist(&conn, &id)?;
+    load_playlist(&conn, &id)
+}
+
+/// Reorder playlists to match the given id order.
+#[tauri::command]
+pub fn playlists_reorder(ids: Vec<String>, db: tauri::State<'_, Database>) -> Result<(), AppError> {
+    let mut conn = db.lock()?;
+    let tx = conn.transaction()?;
+    for (position, id) in ids.iter().enumerate() {
+        tx.execute(
+            "UPDATE playlists SET position = ?2 WHERE id = ?1",
+            params![id, position as i64],
+        )?;
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+/// Delete a playlist and its tracks. The Favorites playlist cannot be deleted.
+#[tauri::command]
+pub fn playlists_delete(id: String, db: tauri::State<'_, Database>) -> Result<(), AppError> {
+    if id == FAVORITES_PLAYLIST_ID {
+        return Err(AppError::invalid_argument("The Favorites playlist cannot be deleted"));
+    }
+    let conn = db.lock()?;
+    conn.execute("DELETE FROM playlists WHERE id = ?1", params![id])?;
+    Ok(())
+}
+
+/// Append a track to a playlist. Tracks already in the playlist are left as is.
+#[tauri::command]
+pub fn playlists_add_track(
+    playlist_id: String,
+    track: PlaylistTrack,
+    db: tauri::State<'_, Database>,
+) -> Result<RhythmPlaylist, AppError> {
+    add_track(&mut *db.lock()?, &playlist_id, &track)
+}
+
+/// Remove a track from a playlist.
+#[tauri::command]
+pub fn playlists_remove_track(
+    playlist_id: String,
+    track_id: String,
+    db: tauri::State<'_, Database>,
+) -> Result<RhythmPlaylist, AppError> {
+    remove_track(&mut *db.lock()?, &playlist_id, &track_id)
+}
+
+/// Reorder the tracks of a playlist to match the given track id order.
+#[tauri::command]
+pub fn playlists_reorder_tracks(
+    playlist_id: String,
+    track_ids: Vec<String>,
+    db: tauri::State<'_, Database>,
+) -> Result<RhythmPlaylist, AppError> {
+    reorder_tracks(&mut *db.lock()?, &playlist_id, &track_ids)
+}
+
+/// One-shot import of the playlists previously kept in localStorage (the JSON
+/// array stored by `rhythmPlaylistsService`). Later calls are no-ops.
+#[tauri::command]
+pub fn playlists_import_local_storage(
+    json: String,
+    db: tauri::State<'_, Database>,
+) -> Result<PlaylistImportResult, AppError> {
+    let playlists: Vec<RhythmPlaylist> = serde_json::from_str(&json)
+        .map_err(|e| AppError::invalid_argument(format!("Failed to parse playlists JSON: {}", e)))?;
+
+    import_playlists(&mut *db.lock()?, &playlists)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::migrations::apply_migrations;
+
+    fn database() -> Connection {
+        let mut conn = Connection::open_in_memory().unwrap();
+        apply_migrations(&mut conn).unwrap();
+        conn
+    }
+
+    fn track(id: &str) -> PlaylistTrack {
+        PlaylistTrack {
+            id: id.to_string(),
+            song_name: format!("Song {}", id),
+            song_author_name: "Artist".into(),
+            level_author_name: "Mapper".into(),
+            bpm: 120.0,
+            duration: 90.0,
+            cover_url: String::new(),
+            diffs: vec![PlaylistTrackDiff {
+                difficulty: "Expert".into(),
+                characteristic: "Standard".into(),
+            }],
+            added_at: String::new(),
+        }
+    }
+
+    fn track_ids(playlist: &RhythmPlaylist) -> Vec<&str> {
+        playlist.tracks.iter().map(|t| t.id.as_str()).collect()
+    }
+
+    #[test]
+    fn names_are_trimmed_and_must_not_be_blank() {
+        assert_eq!(check_name("  Chill  ").unwrap(), "Chill");
+        for blank in ["", " \t\n"] {
+            assert!(matches!(check_name(blank), Err(AppError::InvalidArgument { .. })));
+        }
+    }
+
+    #[test]
+    fn tracks_are_added_removed_and_reordered() {
+        let mut conn = database();
+        for id in ["a", "b", "c"] {
+            add_track(&mut conn, FAVORITES_PLAYLIST_ID, &track(id)).unwrap();
+        }
+        // Adding a track twice keeps the first copy and position
+        let favorites = add_track(&mut conn, FAVORITES_PLAYLIST_ID, &track("a")).unwrap();
+        assert_eq!(track_ids(&favorites), ["a", "b", "c"]);
+        assert_eq!(favorites.tracks[0].diffs[0].difficulty, "Expert");
+        assert!(!favorites.tracks[0].added_at.is_empty());
+
+        let order = ["c".to_string(), "a".to_string(), "b".to_string()];
+        let favorites = reorder_tracks(&mut conn, FAVORITES_PLAYLIST_ID, &order).unwrap();
+        assert_eq!(track_ids(&favorites), ["c", "a", "b"]);
+
+        let favorites = remove_track(&mut conn, FAVORITES_PLAYLIST_ID, "a").unwrap();
+        assert_eq!(track_ids(&favorites), ["c", "b"]);
+        let favorites = add_track(&mut conn, FAVORITES_PLAYLIST_ID, &track("d")).unwrap();
+        assert_eq!(track_ids(&favorites), ["c", "b", "d"]);
+    }
+
+    #[test]
+    fn unknown_playlists_are_errors() {
+        let mut conn = database();
+        let not_found =
+            |result: Result<RhythmPlaylist, AppError>| matches!(result, Err(AppError::NotFound { .. }));
+        assert!(not_found(add_track(&mut conn, "missing", &track("a"))));
+        assert!(not_found(remove_track(&mut conn, "missing", "a")));
+        assert!(not_found(reorder_tracks(&mut conn, "missing", &[])));
+        assert!(load_tracks(&conn, "missing").unwrap().is_empty());
+    }
+
+    #[test]
+    fn local_storage_is_imported_once() {
+        let mut conn = database();
+        let playlists = vec![
+            RhythmPlaylist {
+                id: FAVORITES_PLAYLIST_ID.into(),
+                name: "Favorites".into(),
+                tracks: vec![track("a"), track("b")],
+                created_at: String::new(),
+                updated_at: String::new(),
+            },
+            RhythmPlaylist {
+                id: "road-trip".into(),
+                name: "Road trip".into(),
+                tracks: vec![track("c")],
+                created_at: "2024-01-01T00:00:00.000Z".into(),
+                updated_at: String::new(),
+            },
+        ];
+
+        let result = import_playlists(&mut conn, &playlists).unwrap();
+        assert!(!result.already_imported);
+        // Favorites is seeded by the migrations, so only its tracks are new
+        assert_eq!((result.playlists, result.tracks), (1, 3));
+        let road_trip = load_playlist(&conn, "road-trip").unwrap();
+        assert_eq!(road_trip.created_at, "2024-01-01T00:00:00.000Z");
+        assert!(!road_trip.updated_at.is_empty());
+        assert_eq!(track_ids(&load_playlist(&conn, FAVORITES_PLAYLIST_ID).unwrap()), ["a", "b"]);
+
+        let marker: i64 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM app_meta WHERE key = ?1",
+                params![IMPORT_MARKER_KEY],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(marker, 1);
+
+        let again = import_playlists(&mut conn, &[playlists[1].clone()]).unwrap();
+        assert!(again.already_imported);
+        assert_eq!((again.playlists, again.tracks), (0, 0));
+        assert_eq!(track_ids(&load_playlist(&conn, "road-trip").unwrap()), ["c"]);
+    }
+}
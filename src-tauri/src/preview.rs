@@ -0,0 +1,303 @@
+use serde::{Deserialize, Serialize};
+use tauri::ipc::Request;
+use tauri::AppHandle;
+
+use crate::audio::{self, DecodedAudio};
+use crate::error::{run_blocking, AppError};
+use crate::map_cache;
+use crate::spectrum;
+
+// ---------------------------------------------------------------------------
+// Waveform and spectrogram previews
+// ---------------------------------------------------------------------------
+//
+// A preview is small enough to draw without touching the audio: min/max peaks
+// per column and, optionally, a coarse log-frequency spectrogram. Previews of
+// downloaded maps are cached beside the map as `preview-<width>.json`, a few
+// widths per map.
+
+/// Headers carrying the options of a raw `audio_preview` request.
+pub const WIDTH_HEADER: &str = "x-preview-width";
+pub const SPECTROGRAM_HEADER: &str = "x-preview-spectrogram";
+
+/// Bumped when the preview layout changes, so stale cache files are rebuilt.
+const PREVIEW_FORMAT: u32 = 2;
+const DEFAULT_WIDTH: usize = 1024;
+const MIN_WIDTH: usize = 16;
+const MAX_WIDTH: usize = 8192;
+/// Cached widths kept per map; the least recently written go first.
+const MAX_CACHED_PREVIEWS: usize = 4;
+const CACHE_PREFIX: &str = "preview-";
+const CACHE_SUFFIX: &str = ".json";
+
+const SPECTROGRAM_FRAME_SIZE: usize = 1024;
+const MAX_SPECTROGRAM_COLUMNS: usize = 512;
+const SPECTROGRAM_ROWS: usize = 48;
+const SPECTROGRAM_MIN_HZ: f32 = 40.0;
+const SPECTROGRAM_MAX_HZ: f32 = 16000.0;
+/// Levels this far below the loudest cell map to 0.
+const SPECTROGRAM_RANGE_DB: f32 = 80.0;
+/// Added to magnitudes before taking logs, so silence has a finite level.
+const MAGNITUDE_FLOOR: f32 = 1e-9;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Spectrogram {
+    pub columns: usize,
+    /// Log-spaced frequency bands from `min_hz` (row 0) to `max_hz`.
+    pub rows: usize,
+    pub min_hz: f32,
+    pub max_hz: f32,
+    /// Level per cell from 0 (silent) to 255 (loudest), column by column.
+    pub values: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AudioPreview {
+    pub format: u32,
+    pub duration_secs: f64,
+    pub sample_rate: u32,
+    /// Lowest and highest mono sample per column, in -1..1.
+    pub peaks_min: Vec<f32>,
+    pub peaks_max: Vec<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spectrogram: Option<Spectrogram>,
+}
+
+fn check_width(width: Option<usize>) -> Result<usize, AppError> {
+    let width = width.unwrap_or(DEFAULT_WIDTH);
+    if !(MIN_WIDTH..=MAX_WIDTH).contains(&width) {
+        return Err(AppError::invalid_argument(format!(
+            "Preview width must be between {} and {}, got {}",
+            MIN_WIDTH, MAX_WIDTH, width
+        )));
+    }
+    Ok(width)
+}
+
+/// Samples covered by column `i` of `columns`. Ranges tile the input, so
+/// every column exists even when there are fewer samples than columns.
+fn column_range(len: usize, columns: usize, i: usize) -> std::ops::Range<usize> {
+    i * len / columns..(i + 1) * len / columns
+}
+
+fn peaks(samples: &[f32], width: usize) -> (Vec<f32>, Vec<f32>) {
+    (0..width)
+        .map(|i| {
+            samples[column_range(samples.len(), width, i)]
+                .iter()
+                .fold((0.0f32, 0.0f32), |(low, high), &s| (low.min(s), high.max(s)))
+        })
+        .unzip()
+}
+
+fn spectrogram(audio: &DecodedAudio, columns: usize) -> Spectrogram {
+    let max_hz = SPECTROGRAM_MAX_HZ.min(audio.sample_rate as f32 / 2.0);
+    let bin_hz = audio.sample_rate as f32 / SPECTROGRAM_FRAME_SIZE as f32;
+    let max_bin = SPECTROGRAM_FRAME_SIZE / 2;
+    let ratio = (max_hz / SPECTROGRAM_MIN_HZ).powf(1.0 / SPECTROGRAM_ROWS as f32);
+    let bands: Vec<(usize, usize)> = (0..SPECTROGRAM_ROWS)
+        .map(|row| {
+            let low = SPECTROGRAM_MIN_HZ * ratio.powi(row as i32);
+            let start = ((low / bin_hz).floor() as usize).min(max_bin);
+            let end = ((low * ratio / bin_hz).ceil() as usize).clamp(start + 1, max_bin + 1);
+            (start, end)
+        })
+        .collect();
+
+    let len = audio.samples.len();
+    let centres = (0..columns).map(|i| (2 * i + 1) * len / (2 * columns));
+    let mut levels = Vec::with_capacity(columns * SPECTROGRAM_ROWS);
+    spectrum::for_each_spectrum_at(&audio.samples, SPECTROGRAM_FRAME_SIZE, centres, |magnitudes| {
+        levels.extend(bands.iter().map(|&(start, end)| {
+            let mean = magnitudes[start..end].iter().sum::<f32>() / (end - start) as f32;
+            20.0 * (mean + MAGNITUDE_FLOOR).log10()
+        }));
+    });
+
+    // Silence stays at 0 even when nothing is louder
+    let silence = 20.0 * MAGNITUDE_FLOOR.log10();
+    let loudest = levels.iter().cloned().fold(silence + SPECTROGRAM_RANGE_DB, f32::max);
+    let values = levels
+        .iter()
+        .map(|db| ((db - loudest + SPECTROGRAM_RANGE_DB) / SPECTROGRAM_RANGE_DB * 255.0).clamp(0.0, 255.0) as u8)
+        .collect();
+    Spectrogram {
+        columns,
+        rows: SPECTROGRAM_ROWS,
+        min_hz: SPECTROGRAM_MIN_HZ,
+        max_hz,
+        values,
+    }
+}
+
+/// Peaks `width` columns wide, and a spectrogram of up to 512 columns when
+/// asked for.
+pub fn compute(audio: &DecodedAudio, width: usize, with_spectrogram: bool) -> AudioPreview {
+    let (peaks_min, peaks_max) = peaks(&audio.samples, width);
+    AudioPreview {
+        format: PREVIEW_FORMAT,
+        duration_secs: audio.duration_secs(),
+        sample_rate: audio.sample_rate,
+        peaks_min,
+        peaks_max,
+        spectrogram: with_spectrogram.then(|| spectrogram(audio, width.min(MAX_SPECTROGRAM_COLUMNS))),
+    }
+}
+
+fn read_cached(app_handle: &AppHandle, map_id: &str, filename: &str) -> Result<Option<AudioPreview>, AppError> {
+    let path = map_cache::derived_path(app_handle, map_id, filename)?;
+    // A missing, unreadable or outdated cache file is simply rebuilt
+    Ok(std::fs::read(&path)
+        .ok()
+        .and_then(|bytes| serde_json::from_slice::<AudioPreview>(&bytes).ok())
+        .filter(|preview| preview.format == PREVIEW_FORMAT))
+}
+
+/// Delete all but the `MAX_CACHED_PREVIEWS` newest cached previews of a map.
+/// Failures only leave extra files behind.
+fn prune_cached(app_handle: &AppHandle, map_id: &str) {
+    let (Ok(Some(manifest)), Ok(dir)) = (
+        map_cache::read_manifest(app_handle, map_id),
+        map_cache::map_dir(app_handle, map_id),
+    ) else {
+        return;
+    };
+    let mut cached: Vec<_> = map_cache::derived_files(app_handle, &manifest)
+        .into_iter()
+        .filter(|(name, _)| name.starts_with(CACHE_PREFIX) && name.ends_with(CACHE_SUFFIX))
+        .map(|(name, metadata)| (metadata.modified().ok(), name))
+        .collect();
+    if cached.len() <= MAX_CACHED_PREVIEWS {
+        return;
+    }
+    cached.sort();
+    for (_, name) in &cached[..cached.len() - MAX_CACHED_PREVIEWS] {
+        let _ = std::fs::remove_file(dir.join(name));
+    }
+}
+
+/// Preview of a downloaded map's audio, from the cache when possible. A cached
+/// preview without a spectrogram is rebuilt when one is asked for.
+pub fn map_preview(
+    app_handle: &AppHandle,
+    map_id: &str,
+    width: usize,
+    with_spectrogram: bool,
+) -> Result<AudioPreview, AppError> {
+    let filename = format!("{}{}{}", CACHE_PREFIX, width, CACHE_SUFFIX);
+    if let Some(mut cached) = read_cached(app_handle, map_id, &filename)? {
+        if !with_spectrogram {
+            cached.spectrogram = None;
+            return Ok(cached);
+        }
+        if cached.spectrogram.is_some() {
+            return Ok(cached);
+        }
+    }
+
+    let audio = audio::decode_map_audio(app_handle, map_id)?.into_mono();
+    let preview = compute(&audio, width, with_spectrogram);
+    let json = serde_json::to_vec(&preview)
+        .map_err(|e| AppError::internal(format!("Failed to serialize preview: {}", e)))?;
+    map_cache::write_derived(app_handle, map_id, &filename, &json)?;
+    prune_cached(app_handle, map_id);
+    Ok(preview)
+}
+
+// ---------------------------------------------------------------------------
+// Tauri commands
+// ---------------------------------------------------------------------------
+
+/// Preview of a downloaded map's audio, cached beside the map.
+#[tauri::command]
+pub async fn audio_preview_map(
+    app_handle: AppHandle,
+    map_id: String,
+    width: Option<usize>,
+    spectrogram: Option<bool>,
+) -> Result<AudioPreview, AppError> {
+    let width = check_width(width)?;
+    run_blocking(move || map_preview(&app_handle, &map_id, width, spectrogram.unwrap_or(false))).await
+}
+
+/// Preview of an audio file sent as the raw request body. Width and whether
+/// to include a spectrogram come from the `x-preview-width` and
+/// `x-preview-spectrogram` headers.
+#[tauri::command]
+pub async fn audio_preview(request: Request<'_>) -> Result<AudioPreview, AppError> {
+    let (bytes, extension) = audio::request_audio(&request)?;
+    let width = match audio::header(&request, WIDTH_HEADER) {
+        Some(value) => Some(
+            value
+                .parse::<usize>()
+                .map_err(|_| AppError::invalid_argument(format!("Invalid preview width {}", value)))?,
+        ),
+        None => None,
+    };
+    let width = check_width(width)?;
+    let with_spectrogram = audio::header(&request, SPECTROGRAM_HEADER) == Some("true");
+    run_blocking(move || {
+        let audio = audio::decode_mono(bytes, extension.as_deref())?;
+        Ok(compute(&audio, width, with_spectrogram))
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn audio(samples: Vec<f32>) -> DecodedAudio {
+        DecodedAudio {
+            samples,
+            sample_rate: 22050,
+        }
+    }
+
+    #[test]
+    fn peaks_have_one_column_per_width() {
+        for (len, width) in [(1000, 999), (1000, 1000), (1000, 16), (44100, 1024), (10, 16), (0, 16)] {
+            let (low, high) = peaks(&vec![0.5; len], width);
+            assert_eq!((low.len(), high.len()), (width, width), "{} samples at width {}", len, width);
+        }
+    }
+
+    #[test]
+    fn peaks_are_column_min_and_max() {
+        let samples = [0.1, -0.5, 0.9, 0.2, -0.2, -0.3, 0.4, 0.0];
+        let (low, high) = peaks(&samples, 4);
+        assert_eq!(low, [-0.5, 0.0, -0.3, 0.0]);
+        assert_eq!(high, [0.1, 0.9, 0.0, 0.4]);
+    }
+
+    #[test]
+    fn short_input_leaves_empty_columns_flat() {
+        let (low, high) = peaks(&[-1.0, 1.0], 4);
+        assert_eq!(low, [0.0, -1.0, 0.0, 0.0]);
+        assert_eq!(high, [0.0, 0.0, 0.0, 1.0]);
+        let (low, high) = peaks(&[], 16);
+        assert!(low.iter().chain(&high).all(|&v| v == 0.0));
+    }
+
+    #[test]
+    fn spectrogram_has_the_requested_columns() {
+        let tone: Vec<f32> = (0..30000).map(|i| (i as f32 * 0.3).sin()).collect();
+        for columns in [1, 7, 299, MAX_SPECTROGRAM_COLUMNS] {
+            let spectrogram = spectrogram(&audio(tone.clone()), columns);
+            assert_eq!(spectrogram.columns, columns);
+            assert_eq!(spectrogram.values.len(), columns * SPECTROGRAM_ROWS);
+            assert_eq!(spectrogram.values.iter().max(), Some(&255));
+        }
+    }
+
+    #[test]
+    fn silence_is_blank() {
+        let preview = compute(&audio(Vec::new()), 32, true);
+        assert_eq!(preview.peaks_max.len(), 32);
+        let spectrogram = preview.spectrogram.unwrap();
+        assert_eq!(spectrogram.columns, 32);
+        assert!(spectrogram.values.iter().all(|&v| v == 0));
+    }
+}
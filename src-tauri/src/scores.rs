@@ -0,0 +1,335 @@
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+
+use crate::db::Database;
+use crate::error::AppError;
+
+// ---------------------------------------------------------------------------
+// Score types (mirrors TypeScript RhythmScore)
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RhythmScore {
+    pub id: String,
+    pub map_id: String,
+    pub map_name: String,
+    pub difficulty: String,
+    #[serde(default = "default_lane_mode")]
+    pub lane_mode: i64,
+    pub score: i64,
+    pub max_combo: i64,
+    pub perfect: i64,
+    pub good: i64,
+    pub miss: i64,
+    pub grade: String,
+    pub date: String,
+}
+
+fn default_lane_mode() -> i64 {
+    3
+}
+
+/// Difference between a score and the previous personal best on the same chart.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PersonalBestDelta {
+    pub score: i64,
+    pub max_combo: i64,
+    pub perfect: i64,
+    pub good: i64,
+    pub miss: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScoreSubmitResult {
+    pub score: RhythmScore,
+    pub previous_best: Option<RhythmScore>,
+    pub is_personal_best: bool,
+    pub delta: Option<PersonalBestDelta>,
+}
+
+// ---------------------------------------------------------------------------
+// Row mapping and queries
+// ---------------------------------------------------------------------------
+
+const SCORE_COLUMNS: &str =
+    "id, map_id, map_name, difficulty, lane_mode, score, max_combo, perfect, good, miss, grade, date";
+
+fn row_to_score(row: &Row<'_>) -> rusqlite::Result<RhythmScore> {
+    Ok(RhythmScore {
+        id: row.get(0)?,
+        map_id: row.get(1)?,
+        map_name: row.get(2)?,
+        difficulty: row.get(3)?,
+        lane_mode: row.get(4)?,
+        score: row.get(5)?,
+        max_combo: row.get(6)?,
+        perfect: row.get(7)?,
+        good: row.get(8)?,
+        miss: row.get(9)?,
+        grade: row.get(10)?,
+        date: row.get(11)?,
+    })
+}
+
+/// Best score on a chart, ignoring the score `except_id` if given.
+fn personal_best(
+    conn: &Connection,
+    map_id: &str,
+    difficulty: &str,
+    lane_mode: i64,
+    except_id: Option<&str>,
+) -> Result<Option<RhythmScore>, AppError> {
+    let best = conn
+        .query_row(
+            &format!(
+                "SELECT {} FROM scores
+                 WHERE map_id = ?1 AND difficulty = ?2 AND lane_mode = ?3 AND (?4 IS NULL OR id != ?4)
+                 ORDER BY score DESC, date ASC LIMIT 1",
+                SCORE_COLUMNS
+            ),
+            params![map_id, difficulty, lane_mode, except_id],
+            row_to_score,
+        )
+        .optional()?;
+    Ok(best)
+}
+
+/// Save a score and return the stored row. Submitting an id again (e.g. a
+/// retried request) keeps and returns the row saved the first time.
+fn insert_score(conn: &Connection, score: &RhythmScore) -> Result<RhythmScore, AppError> {
+    conn.execute(
+        &format!(
+            "INSERT OR IGNORE INTO scores ({}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+            SCORE_COLUMNS
+        ),
+        params![
+            score.id,
+            score.map_id,
+            score.map_name,
+            score.difficulty,
+            score.lane_mode,
+            score.score,
+            score.max_combo,
+            score.perfect,
+            score.good,
+            score.miss,
+            score.grade,
+            score.date,
+        ],
+    )?;
+    let saved = conn.query_row(
+        &format!("SELECT {} FROM scores WHERE id = ?1", SCORE_COLUMNS),
+        params![score.id],
+        row_to_score,
+    )?;
+    Ok(saved)
+}
+
+fn submit_score(conn: &Connection, score: &RhythmScore) -> Result<ScoreSubmitResult, AppError> {
+    let score = insert_score(conn, score)?;
+    let previous_best =
+        personal_best(conn, &score.map_id, &score.difficulty, score.lane_mode, Some(&score.id))?;
+
+    let is_personal_best = previous_best
+        .as_ref()
+        .is_none_or(|best| score.score > best.score);
+    let delta = previous_best.as_ref().map(|best| delta_between(&score, best));
+
+    Ok(ScoreSubmitResult {
+        score,
+        previous_best,
+        is_personal_best,
+        delta,
+    })
+}
+
+fn map_scores(
+    conn: &Connection,
+    map_id: &str,
+    difficulty: Option<&str>,
+    lane_mode: Option<i64>,
+) -> Result<Vec<RhythmScore>, AppError> {
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM scores
+             WHERE map_id = ?1 AND (?2 IS NULL OR difficulty = ?2) AND (?3 IS NULL OR lane_mode = ?3)
+             ORDER BY score DESC, date ASC",
+            SCORE_COLUMNS
+        ))?;
+    let scores = stmt
+        .query_map(params![map_id, difficulty, lane_mode], row_to_score)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(scores)
+}
+
+fn recent_scores(conn: &Connection, limit: i64) -> Result<Vec<RhythmScore>, AppError> {
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM scores ORDER BY date DESC LIMIT ?1",
+            SCORE_COLUMNS
+        ))?;
+    let scores = stmt
+        .query_map(params![limit], row_to_score)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(scores)
+}
+
+fn delta_between(score: &RhythmScore, best: &RhythmScore) -> PersonalBestDelta {
+    PersonalBestDelta {
+        score: score.score - best.score,
+        max_combo: score.max_combo - best.max_combo,
+        perfect: score.perfect - best.perfect,
+        good: score.good - best.good,
+        miss: score.miss - best.miss,
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Tauri commands
+// ---------------------------------------------------------------------------
+
+/// Save a score and report how it compares to the previous personal best.
+/// Resubmitting a saved score reports on the saved copy.
+#[tauri::command]
+pub fn scores_submit(
+    score: RhythmScore,
+    db: tauri::State<'_, Database>,
+) -> Result<ScoreSubmitResult, AppError> {
+    submit_score(&*db.lock()?, &score)
+}
+
+/// All scores for a map, best first. Difficulty and lane mode narrow the chart.
+#[tauri::command]
+pub fn scores_for_map(
+    map_id: String,
+    difficulty: Option<String>,
+    lane_mode: Option<i64>,
+    db: tauri::State<'_, Database>,
+) -> Result<Vec<RhythmScore>, AppError> {
+    map_scores(&*db.lock()?, &map_id, difficulty.as_deref(), lane_mode)
+}
+
+/// Best score for a single chart (map + difficulty + lane mode).
+#[tauri::command]
+pub fn scores_personal_best(
+    map_id: String,
+    difficulty: String,
+    lane_mode: i64,
+    db: tauri::State<'_, Database>,
+) -> Result<Option<RhythmScore>, AppError> {
+    personal_best(&*db.lock()?, &map_id, &difficulty, lane_mode, None)
+}
+
+/// Most recently played scores across all maps.
+#[tauri::command]
+pub fn scores_recent(
+    limit: Option<i64>,
+    db: tauri::State<'_, Database>,
+) -> Result<Vec<RhythmScore>, AppError> {
+    recent_scores(&*db.lock()?, limit.unwrap_or(50))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::migrations::apply_migrations;
+
+    fn database() -> Connection {
+        let mut conn = Connection::open_in_memory().unwrap();
+        apply_migrations(&mut conn).unwrap();
+        conn
+    }
+
+    fn play(id: &str, score: i64, date: &str) -> RhythmScore {
+        RhythmScore {
+            id: id.to_string(),
+            map_id: "1a2b".into(),
+            map_name: "Song".into(),
+            difficulty: "Expert".into(),
+            lane_mode: 4,
+            score,
+            max_combo: score / 100,
+            perfect: score / 200,
+            good: 10,
+            miss: 5,
+            grade: "A".into(),
+            date: date.to_string(),
+        }
+    }
+
+    fn ids(scores: &[RhythmScore]) -> Vec<&str> {
+        scores.iter().map(|s| s.id.as_str()).collect()
+    }
+
+    #[test]
+    fn first_score_is_a_personal_best_without_delta() {
+        let conn = database();
+        let result = submit_score(&conn, &play("a", 50_000, "2024-01-01T00:00:00Z")).unwrap();
+        assert!(result.is_personal_best);
+        assert!(result.previous_best.is_none());
+        assert!(result.delta.is_none());
+        assert_eq!(result.score.score, 50_000);
+    }
+
+    #[test]
+    fn delta_is_against_the_previous_best() {
+        let conn = database();
+        submit_score(&conn, &play("a", 50_000, "2024-01-01T00:00:00Z")).unwrap();
+        submit_score(&conn, &play("b", 40_000, "2024-01-02T00:00:00Z")).unwrap();
+
+        let better = submit_score(&conn, &play("c", 60_000, "2024-01-03T00:00:00Z")).unwrap();
+        assert!(better.is_personal_best);
+        assert_eq!(better.previous_best.unwrap().id, "a");
+        let delta = better.delta.unwrap();
+        assert_eq!((delta.score, delta.max_combo, delta.perfect), (10_000, 100, 50));
+        assert_eq!((delta.good, delta.miss), (0, 0));
+
+        let worse = submit_score(&conn, &play("d", 55_000, "2024-01-04T00:00:00Z")).unwrap();
+        assert!(!worse.is_personal_best);
+        assert_eq!(worse.delta.unwrap().score, -5_000);
+
+        // Equalling the best isn't a new personal best
+        let tie = submit_score(&conn, &play("e", 60_000, "2024-01-05T00:00:00Z")).unwrap();
+        assert!(!tie.is_personal_best);
+    }
+
+    #[test]
+    fn resubmitting_returns_the_saved_score() {
+        let conn = database();
+        submit_score(&conn, &play("a", 50_000, "2024-01-01T00:00:00Z")).unwrap();
+        submit_score(&conn, &play("b", 60_000, "2024-01-02T00:00:00Z")).unwrap();
+
+        let again = submit_score(&conn, &play("b", 99_999, "2024-01-09T00:00:00Z")).unwrap();
+        assert_eq!(again.score.score, 60_000);
+        assert_eq!(again.score.date, "2024-01-02T00:00:00Z");
+        assert_eq!(again.previous_best.unwrap().id, "a");
+        assert!(again.is_personal_best);
+        assert_eq!(map_scores(&conn, "1a2b", None, None).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn scores_are_listed_by_chart_and_date() {
+        let conn = database();
+        submit_score(&conn, &play("a", 50_000, "2024-01-01T00:00:00Z")).unwrap();
+        submit_score(&conn, &play("b", 70_000, "2024-01-03T00:00:00Z")).unwrap();
+        let other_mode = RhythmScore {
+            lane_mode: 3,
+            ..play("c", 90_000, "2024-01-02T00:00:00Z")
+        };
+        let other_map = RhythmScore {
+            map_id: "3c4d".into(),
+            ..play("d", 10_000, "2024-01-04T00:00:00Z")
+        };
+        submit_score(&conn, &other_mode).unwrap();
+        submit_score(&conn, &other_map).unwrap();
+
+        assert_eq!(ids(&map_scores(&conn, "1a2b", None, None).unwrap()), ["c", "b", "a"]);
+        assert_eq!(ids(&map_scores(&conn, "1a2b", Some("Expert"), Some(4)).unwrap()), ["b", "a"]);
+        assert!(map_scores(&conn, "1a2b", Some("Easy"), None).unwrap().is_empty());
+        assert_eq!(personal_best(&conn, "1a2b", "Expert", 4, None).unwrap().unwrap().id, "b");
+        assert_eq!(ids(&recent_scores(&conn, 2).unwrap()), ["d", "b"]);
+    }
+}
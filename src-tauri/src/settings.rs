@@ -0,0 +1,416 @@
+use serde::{Deserialize, Serialize};
+use std::sync::RwLock;
+use tauri::AppHandle;
+use tauri_plugin_store::StoreExt;
+
+use crate::cache;
+use crate::error::AppError;
+use crate::http::{HttpClient, MIN_RATE_PER_SEC};
+
+// ---------------------------------------------------------------------------
+// Backend settings (persisted via tauri-plugin-store)
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackendSettings {
+    #[serde(default)]
+    pub beatsaver: BeatSaverSettings,
+    #[serde(default)]
+    pub http: HttpSettings,
+    /// When set, BeatSaver commands never touch the network and answer from
+    /// the metadata cache and downloaded maps only.
+    #[serde(default)]
+    pub offline: bool,
+    #[serde(default)]
+    pub metadata_cache: MetadataCacheSettings,
+    #[serde(default)]
+    pub download_cache: DownloadCacheSettings,
+    #[serde(default)]
+    pub zip_limits: ZipLimitsSettings,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BeatSaverSettings {
+    #[serde(default = "default_api_base")]
+    pub api_base: String,
+    /// When set, map downloads are fetched from this host instead of the one
+    /// in the map's `downloadURL`.
+    #[serde(default)]
+    pub cdn_base: Option<String>,
+    /// Fallback endpoints tried in order when the primary one fails.
+    #[serde(default)]
+    pub mirrors: Vec<BeatSaverMirror>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BeatSaverMirror {
+    pub api_base: String,
+    #[serde(default)]
+    pub cdn_base: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HttpSettings {
+    #[serde(default = "default_connect_timeout_secs")]
+    pub connect_timeout_secs: u64,
+    /// Maximum time to wait between reads of a response body.
+    #[serde(default = "default_read_timeout_secs")]
+    pub read_timeout_secs: u64,
+    /// Optional proxy URL (e.g. `http://127.0.0.1:8080`) used for all requests.
+    #[serde(default)]
+    pub proxy: Option<String>,
+    /// Total attempts per request, including the first one.
+    #[serde(default = "default_max_attempts")]
+    pub max_attempts: u32,
+    #[serde(default = "default_retry_base_delay_ms")]
+    pub retry_base_delay_ms: u64,
+    #[serde(default = "default_retry_max_delay_ms")]
+    pub retry_max_delay_ms: u64,
+    /// Sustained request rate for the client-side token bucket (0 disables it).
+    #[serde(default = "default_rate_limit_per_sec")]
+    pub rate_limit_per_sec: f64,
+    #[serde(default = "default_rate_limit_burst")]
+    pub rate_limit_burst: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MetadataCacheSettings {
+    /// How long a cached map (by id) is served without refetching.
+    #[serde(default = "default_map_ttl_secs")]
+    pub map_ttl_secs: u64,
+    /// How long a cached search/browse response is served without refetching.
+    #[serde(default = "default_query_ttl_secs")]
+    pub query_ttl_secs: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DownloadCacheSettings {
+    /// Disk quota for downloaded maps; least recently played maps outside any
+    /// playlist are evicted above it. 0 disables the quota.
+    #[serde(default = "default_max_bytes")]
+    pub max_bytes: u64,
+}
+
+/// Limits applied while extracting a map ZIP, so a malicious archive can't
+/// exhaust memory.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ZipLimitsSettings {
+    #[serde(default = "default_max_entries")]
+    pub max_entries: usize,
+    /// Total bytes extracted across all entries.
+    #[serde(default = "default_max_total_bytes")]
+    pub max_total_bytes: u64,
+    /// Largest uncompressed/compressed ratio allowed for an entry past 1 MiB.
+    #[serde(default = "default_max_compression_ratio")]
+    pub max_compression_ratio: u64,
+    #[serde(default = "default_max_audio_bytes")]
+    pub max_audio_bytes: u64,
+}
+
+pub const DEFAULT_API_BASE: &str = "https://api.beatsaver.com";
+
+fn default_api_base() -> String {
+    DEFAULT_API_BASE.to_string()
+}
+
+fn default_connect_timeout_secs() -> u64 {
+    10
+}
+
+fn default_read_timeout_secs() -> u64 {
+    30
+}
+
+fn default_max_attempts() -> u32 {
+    4
+}
+
+fn default_retry_base_delay_ms() -> u64 {
+    500
+}
+
+fn default_retry_max_delay_ms() -> u64 {
+    30_000
+}
+
+fn default_rate_limit_per_sec() -> f64 {
+    5.0
+}
+
+fn default_rate_limit_burst() -> u32 {
+    10
+}
+
+fn default_map_ttl_secs() -> u64 {
+    24 * 60 * 60
+}
+
+fn default_query_ttl_secs() -> u64 {
+    10 * 60
+}
+
+fn default_max_bytes() -> u64 {
+    2 * 1024 * 1024 * 1024
+}
+
+fn default_max_entries() -> usize {
+    256
+}
+
+fn default_max_total_bytes() -> u64 {
+    256 * 1024 * 1024
+}
+
+fn default_max_compression_ratio() -> u64 {
+    100
+}
+
+fn default_max_audio_bytes() -> u64 {
+    64 * 1024 * 1024
+}
+
+impl Default for ZipLimitsSettings {
+    fn default() -> Self {
+        Self {
+            max_entries: default_max_entries(),
+            max_total_bytes: default_max_total_bytes(),
+            max_compression_ratio: default_max_compression_ratio(),
+            max_audio_bytes: default_max_audio_bytes(),
+        }
+    }
+}
+
+impl Default for DownloadCacheSettings {
+    fn default() -> Self {
+        Self {
+            max_bytes: default_max_bytes(),
+        }
+    }
+}
+
+impl Default for MetadataCacheSettings {
+    fn default() -> Self {
+        Self {
+            map_ttl_secs: default_map_ttl_secs(),
+            query_ttl_secs: default_query_ttl_secs(),
+        }
+    }
+}
+
+impl Default for HttpSettings {
+    fn default() -> Self {
+        Self {
+            connect_timeout_secs: default_connect_timeout_secs(),
+            read_timeout_secs: default_read_timeout_secs(),
+            proxy: None,
+            max_attempts: default_max_attempts(),
+            retry_base_delay_ms: default_retry_base_delay_ms(),
+            retry_max_delay_ms: default_retry_max_delay_ms(),
+            rate_limit_per_sec: default_rate_limit_per_sec(),
+            rate_limit_burst: default_rate_limit_burst(),
+        }
+    }
+}
+
+impl Default for BeatSaverSettings {
+    fn default() -> Self {
+        Self {
+            api_base: default_api_base(),
+            cdn_base: None,
+            mirrors: Vec::new(),
+        }
+    }
+}
+
+impl BackendSettings {
+    fn normalize(self) -> Result<Self, AppError> {
+        if self.http.connect_timeout_secs == 0 || self.http.read_timeout_secs == 0 {
+            return Err(AppError::invalid_argument("HTTP timeouts must be at least one second"));
+        }
+        let rate = self.http.rate_limit_per_sec;
+        if !rate.is_finite() || (rate > 0.0 && rate < MIN_RATE_PER_SEC) {
+            return Err(AppError::invalid_argument(format!(
+                "Rate limit must be 0 (off) or at least {} requests per second",
+                MIN_RATE_PER_SEC
+            )));
+        }
+        let zip = &self.zip_limits;
+        if zip.max_entries == 0
+            || zip.max_total_bytes == 0
+            || zip.max_compression_ratio == 0
+            || zip.max_audio_bytes == 0
+        {
+            return Err(AppError::invalid_argument("ZIP limits must be greater than zero"));
+        }
+        Ok(Self {
+            beatsaver: self.beatsaver.normalize()?,
+            ..self
+        })
+    }
+}
+
+impl BeatSaverSettings {
+    /// API base URLs in the order they should be tried.
+    pub fn api_bases(&self) -> Vec<String> {
+        let mut bases = vec![self.api_base.clone()];
+        for mirror in &self.mirrors {
+            if !bases.contains(&mirror.api_base) {
+                bases.push(mirror.api_base.clone());
+            }
+        }
+        bases
+    }
+
+    /// Candidate URLs for a map download in the order they should be tried:
+    /// the configured CDN, the original URL, then each mirror's CDN.
+    pub fn download_urls(&self, download_url: &str) -> Vec<String> {
+        let mut urls = Vec::new();
+        if let Some(cdn) = &self.cdn_base {
+            urls.extend(rebase_url(download_url, cdn));
+        }
+        urls.push(download_url.to_string());
+        for cdn in self.mirrors.iter().filter_map(|m| m.cdn_base.as_ref()) {
+            urls.extend(rebase_url(download_url, cdn));
+        }
+        let mut seen = std::collections::HashSet::new();
+        urls.retain(|u| seen.insert(u.clone()));
+        urls
+    }
+
+    fn normalize(mut self) -> Result<Self, AppError> {
+        self.api_base = normalize_base(&self.api_base)?;
+        self.cdn_base = self.cdn_base.as_deref().map(normalize_base).transpose()?;
+        for mirror in &mut self.mirrors {
+            mirror.api_base = normalize_base(&mirror.api_base)?;
+            mirror.cdn_base = mirror.cdn_base.as_deref().map(normalize_base).transpose()?;
+        }
+        Ok(self)
+    }
+}
+
+/// Validate a base URL and strip any trailing slash.
+fn normalize_base(base: &str) -> Result<String, AppError> {
+    let trimmed = base.trim().trim_end_matches('/');
+    let url = reqwest::Url::parse(trimmed)
+        .map_err(|e| AppError::invalid_argument(format!("Invalid URL {}: {}", base, e)))?;
+    if url.scheme() != "http" && url.scheme() != "https" {
+        return Err(AppError::invalid_argument(format!("Unsupported URL scheme in {}", base)));
+    }
+    Ok(trimmed.to_string())
+}
+
+/// Replace the scheme and host of `url` with `base`, keeping the path and query.
+fn rebase_url(url: &str, base: &str) -> Option<String> {
+    let parsed = reqwest::Url::parse(url).ok()?;
+    let mut rebased = format!("{}{}", base, parsed.path());
+    if let Some(query) = parsed.query() {
+        rebased.push('?');
+        rebased.push_str(query);
+    }
+    Some(rebased)
+}
+
+// ---------------------------------------------------------------------------
+// Managed state and persistence
+// ---------------------------------------------------------------------------
+
+pub struct SettingsState(pub RwLock<BackendSettings>);
+
+const SETTINGS_STORE: &str = "backend-settings.json";
+const SETTINGS_KEY: &str = "settings";
+
+impl SettingsState {
+    /// Load persisted settings, falling back to defaults.
+    pub fn load(app_handle: &AppHandle) -> Self {
+        let settings = app_handle
+            .store(SETTINGS_STORE)
+            .ok()
+            .and_then(|store| store.get(SETTINGS_KEY))
+            .and_then(|value| serde_json::from_value(value).ok())
+            .unwrap_or_default();
+        Self(RwLock::new(settings))
+    }
+
+    pub fn get(&self) -> BackendSettings {
+        self.0.read().map(|s| s.clone()).unwrap_or_default()
+    }
+}
+
+fn persist(app_handle: &AppHandle, settings: &BackendSettings) -> Result<(), AppError> {
+    let store = app_handle
+        .store(SETTINGS_STORE)
+        .map_err(|e| AppError::io(format!("Failed to open settings store: {}", e)))?;
+    let value = serde_json::to_value(settings)
+        .map_err(|e| AppError::internal(format!("Failed to serialize settings: {}", e)))?;
+    store.set(SETTINGS_KEY, value);
+    store
+        .save()
+        .map_err(|e| AppError::io(format!("Failed to save settings: {}", e)))
+}
+
+// ---------------------------------------------------------------------------
+// Tauri commands
+// ---------------------------------------------------------------------------
+
+/// Get the current backend settings.
+#[tauri::command]
+pub fn settings_get(state: tauri::State<'_, SettingsState>) -> Result<BackendSettings, AppError> {
+    Ok(state.get())
+}
+
+/// Replace the backend settings, validate them and persist to disk.
+#[tauri::command]
+pub fn settings_set(
+    settings: BackendSettings,
+    state: tauri::State<'_, SettingsState>,
+    http: tauri::State<'_, HttpClient>,
+    app_handle: AppHandle,
+) -> Result<BackendSettings, AppError> {
+    let settings = settings.normalize()?;
+    http.reconfigure(&settings.http)?;
+    persist(&app_handle, &settings)?;
+    *state
+        .0
+        .write()
+        .map_err(|_| AppError::internal("Settings lock poisoned"))? = settings.clone();
+    // A lowered quota applies now rather than at the next download
+    tauri::async_runtime::spawn_blocking(move || cache::apply_quota(&app_handle, None));
+    Ok(settings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn with_rate(rate_limit_per_sec: f64) -> BackendSettings {
+        let mut settings = BackendSettings::default();
+        settings.http.rate_limit_per_sec = rate_limit_per_sec;
+        settings
+    }
+
+    #[test]
+    fn rate_limit_is_off_or_at_least_the_minimum() {
+        for rate in [0.0, -1.0, MIN_RATE_PER_SEC, 5.0] {
+            assert!(with_rate(rate).normalize().is_ok(), "{}", rate);
+        }
+        for rate in [1e-300, MIN_RATE_PER_SEC / 2.0, f64::INFINITY, f64::NAN] {
+            let result = with_rate(rate).normalize();
+            assert!(matches!(result, Err(AppError::InvalidArgument { .. })), "{}", rate);
+        }
+    }
+
+    #[test]
+    fn base_urls_are_trimmed_and_must_be_http() {
+        assert_eq!(normalize_base(" https://api.beatsaver.com/ ").unwrap(), "https://api.beatsaver.com");
+        for base in ["ftp://example.com", "not a url"] {
+            assert!(matches!(normalize_base(base), Err(AppError::InvalidArgument { .. })), "{}", base);
+        }
+    }
+}
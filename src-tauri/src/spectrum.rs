@@ -0,0 +1,100 @@
+use realfft::RealFftPlanner;
+
+// ---------------------------------------------------------------------------
+// Short-time spectra and spectral flux
+// ---------------------------------------------------------------------------
+
+pub const FRAME_SIZE: usize = 2048;
+pub const HOP_SIZE: usize = 512;
+/// Gain applied before log compression of magnitudes.
+const LOG_GAIN: f32 = 100.0;
+
+fn hann(size: usize) -> Vec<f32> {
+    (0..size)
+        .map(|i| 0.5 - 0.5 * (std::f32::consts::TAU * i as f32 / size as f32).cos())
+        .collect()
+}
+
+/// Call `f` with the magnitude spectrum of each windowed frame. Frames are
+/// centred on multiples of `hop`, so frame `i` describes time `i * hop / rate`.
+pub fn for_each_spectrum(samples: &[f32], frame_size: usize, hop: usize, f: impl FnMut(&[f32])) {
+    let centres = (0..samples.len().div_ceil(hop)).map(|frame| frame * hop);
+    for_each_spectrum_at(samples, frame_size, centres, f);
+}
+
+/// Call `f` with the magnitude spectrum of a windowed frame centred on each
+/// sample index in `centres`. Samples outside `samples` count as silence.
+pub fn for_each_spectrum_at(
+    samples: &[f32],
+    frame_size: usize,
+    centres: impl IntoIterator<Item = usize>,
+    mut f: impl FnMut(&[f32]),
+) {
+    let fft = RealFftPlanner::<f32>::new().plan_fft_forward(frame_size);
+    let window = hann(frame_size);
+    let mut input = fft.make_input_vec();
+    let mut output = fft.make_output_vec();
+    let mut magnitudes = vec![0.0f32; output.len()];
+    let half = frame_size / 2;
+
+    for centre in centres {
+        for (k, x) in input.iter_mut().enumerate() {
+            let sample = (centre + k)
+                .checked_sub(half)
+                .and_then(|i| samples.get(i))
+                .copied()
+                .unwrap_or(0.0);
+            *x = sample * window[k];
+        }
+        // Buffer lengths come from the plan, so processing can't fail
+        let _ = fft.process(&mut input, &mut output);
+        for (m, c) in magnitudes.iter_mut().zip(&output) {
+            *m = c.norm();
+        }
+        f(&magnitudes);
+    }
+}
+
+/// Onset strength per frequency band.
+#[derive(Debug, Clone)]
+pub struct SpectralFlux {
+    /// One envelope per requested band, all the same length.
+    pub bands: Vec<Vec<f32>>,
+    /// Envelope frames per second.
+    pub frame_rate: f64,
+}
+
+/// Half-wave rectified spectral flux of log-compressed magnitudes, summed
+/// over each `(low_hz, high_hz)` band.
+pub fn spectral_flux(samples: &[f32], sample_rate: u32, bands: &[(f32, f32)]) -> SpectralFlux {
+    let bin_hz = sample_rate as f32 / FRAME_SIZE as f32;
+    let max_bin = FRAME_SIZE / 2;
+    let ranges: Vec<(usize, usize)> = bands
+        .iter()
+        .map(|&(low, high)| {
+            let start = ((low / bin_hz).floor() as usize).min(max_bin);
+            let end = ((high / bin_hz).ceil() as usize).clamp(start + 1, max_bin + 1);
+            (start, end)
+        })
+        .collect();
+
+    let mut envelopes = vec![Vec::new(); bands.len()];
+    let mut previous: Vec<f32> = Vec::new();
+    for_each_spectrum(samples, FRAME_SIZE, HOP_SIZE, |magnitudes| {
+        let current: Vec<f32> = magnitudes.iter().map(|m| (1.0 + LOG_GAIN * m).ln()).collect();
+        for (envelope, &(start, end)) in envelopes.iter_mut().zip(&ranges) {
+            let flux = if previous.is_empty() {
+                0.0
+            } else {
+                (start..end).map(|b| (current[b] - previous[b]).max(0.0)).sum()
+            };
+            envelope.push(flux);
+        }
+        previous = current;
+    });
+
+    SpectralFlux {
+        bands: envelopes,
+        frame_rate: sample_rate as f64 / HOP_SIZE as f64,
+    }
+}
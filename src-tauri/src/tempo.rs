@@ -0,0 +1,411 @@
+use serde::Serialize;
+
+// ---------------------------------------------------------------------------
+// Tempo, beat grid and tempo map detection
+// ---------------------------------------------------------------------------
+//
+// Spectral flux gives an onset envelope; its autocorrelation picks the global
+// tempo, and a dynamic-programming beat tracker (Ellis 2007) places beats that
+// may drift from that tempo, as live recordings do.
+
+const MIN_BPM: f64 = 60.0;
+const MAX_BPM: f64 = 200.0;
+/// Centre of the tempo prior, used to settle half/double tempo ambiguity.
+const PRIOR_BPM: f64 = 120.0;
+/// How strongly the beat tracker holds beats to the estimated period.
+const TIGHTNESS: f64 = 100.0;
+/// Local tempo changes within this fraction stay in one tempo map segment.
+const SEGMENT_TOLERANCE: f64 = 0.02;
+/// Beat intervals considered when estimating the local tempo.
+const LOCAL_WINDOW_BEATS: usize = 8;
+/// Downbeats are found assuming 4/4.
+const BEATS_PER_BAR: usize = 4;
+/// Kick and bass range used to find downbeats.
+pub const LOW_BAND_HZ: (f32, f32) = (0.0, 200.0);
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TempoSegment {
+    pub start_secs: f64,
+    pub bpm: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TempoAnalysis {
+    /// Overall tempo, from the average beat interval.
+    pub bpm: f64,
+    /// Time of the first tracked beat.
+    pub offset_secs: f64,
+    /// Time of the first downbeat.
+    pub downbeat_offset_secs: f64,
+    /// Every tracked beat, in seconds.
+    pub beats: Vec<f64>,
+    /// Index into `beats` of the first downbeat.
+    pub downbeat_index: usize,
+    /// Tempo changes over the song; always starts with one segment.
+    pub tempo_map: Vec<TempoSegment>,
+    pub duration_secs: f64,
+}
+
+fn mean(values: &[f64]) -> f64 {
+    if values.is_empty() {
+        0.0
+    } else {
+        values.iter().sum::<f64>() / values.len() as f64
+    }
+}
+
+fn median(values: &[f64]) -> f64 {
+    let mut sorted = values.to_vec();
+    sorted.sort_by(f64::total_cmp);
+    let middle = sorted.len() / 2;
+    match sorted.len() {
+        0 => 0.0,
+        n if n % 2 == 0 => (sorted[middle - 1] + sorted[middle]) / 2.0,
+        _ => sorted[middle],
+    }
+}
+
+/// Remove the background level (the median) and scale the envelope to unit
+/// standard deviation, so noise and silence score close to zero.
+pub fn normalize(envelope: &[f32]) -> Vec<f64> {
+    let values: Vec<f64> = envelope.iter().map(|&v| v as f64).collect();
+    let floor = median(&values);
+    let values: Vec<f64> = values.iter().map(|v| (v - floor).max(0.0)).collect();
+    let m = mean(&values);
+    let std = (values.iter().map(|v| (v - m).powi(2)).sum::<f64>() / values.len().max(1) as f64).sqrt();
+    if std > 0.0 {
+        values.iter().map(|v| v / std).collect()
+    } else {
+        values
+    }
+}
+
+/// Gaussian smoothing, so beats a few frames off an onset still score.
+fn smooth(envelope: &[f64], period: f64) -> Vec<f64> {
+    let sigma = (period / 32.0).max(1.0);
+    let radius = (period / 2.0).ceil() as isize;
+    let kernel: Vec<f64> = (-radius..=radius)
+        .map(|i| (-0.5 * (i as f64 / sigma).powi(2)).exp())
+        .collect();
+    (0..envelope.len() as isize)
+        .map(|t| {
+            kernel
+                .iter()
+                .enumerate()
+                .filter_map(|(k, w)| {
+                    let i = t + k as isize - radius;
+                    (i >= 0).then(|| envelope.get(i as usize).map(|v| v * w)).flatten()
+                })
+                .sum()
+        })
+        .collect()
+}
+
+/// Beat period in envelope frames from the autocorrelation peak, weighted by
+/// a log-normal prior around `PRIOR_BPM`.
+fn estimate_period(envelope: &[f64], frame_rate: f64) -> f64 {
+    let fallback = 60.0 * frame_rate / PRIOR_BPM;
+    let min_lag = (60.0 * frame_rate / MAX_BPM).floor() as usize;
+    let max_lag = ((60.0 * frame_rate / MIN_BPM).ceil() as usize).min(envelope.len().saturating_sub(1));
+    if max_lag <= min_lag + 2 {
+        return fallback;
+    }
+
+    let m = mean(envelope);
+    let centred: Vec<f64> = envelope.iter().map(|v| v - m).collect();
+    let score = |lag: usize| {
+        let n = centred.len() - lag;
+        let ac = (0..n).map(|i| centred[i] * centred[i + lag]).sum::<f64>() / n as f64;
+        let bpm = 60.0 * frame_rate / lag as f64;
+        ac * (-0.5 * (bpm / PRIOR_BPM).log2().powi(2)).exp()
+    };
+    let scores: Vec<f64> = (min_lag..=max_lag).map(score).collect();
+    let Some(best) = (1..scores.len() - 1).max_by(|&a, &b| scores[a].total_cmp(&scores[b])) else {
+        return fallback;
+    };
+
+    // Parabolic interpolation between neighbouring lags
+    let (left, centre, right) = (scores[best - 1], scores[best], scores[best + 1]);
+    let denominator = left - 2.0 * centre + right;
+    let shift = if denominator.abs() > f64::EPSILON {
+        (0.5 * (left - right) / denominator).clamp(-0.5, 0.5)
+    } else {
+        0.0
+    };
+    (min_lag + best) as f64 + shift
+}
+
+/// Dynamic-programming beat tracker. Each frame's score is its onset strength
+/// plus the best earlier beat, penalised by how far the interval strays from
+/// `period` on a log scale. Returns beat frames.
+fn track_beats(envelope: &[f64], period: f64) -> Vec<usize> {
+    let local = smooth(envelope, period);
+    let n = local.len();
+    let min_interval = ((period / 2.0).round() as usize).max(1);
+    let max_interval = ((period * 2.0).round() as usize).max(min_interval + 1);
+
+    let mut cumulative = vec![0.0f64; n];
+    let mut backlink: Vec<Option<usize>> = vec![None; n];
+    for t in 0..n {
+        let mut best: Option<(f64, usize)> = None;
+        if t >= min_interval {
+            let first = t.saturating_sub(max_interval);
+            for (previous, &previous_score) in cumulative[first..=t - min_interval].iter().enumerate() {
+                let previous = first + previous;
+                let interval = (t - previous) as f64;
+                let score = previous_score - TIGHTNESS * (interval / period).ln().powi(2);
+                if best.is_none_or(|(s, _)| score > s) {
+                    best = Some((score, previous));
+                }
+            }
+        }
+        match best {
+            Some((score, previous)) if score > 0.0 => {
+                cumulative[t] = local[t] + score;
+                backlink[t] = Some(previous);
+            }
+            _ => cumulative[t] = local[t],
+        }
+    }
+
+    // End on the last local maximum that is reasonably strong
+    let peaks: Vec<usize> = (1..n.saturating_sub(1))
+        .filter(|&t| cumulative[t] > cumulative[t - 1] && cumulative[t] >= cumulative[t + 1])
+        .collect();
+    let threshold = 0.5 * median(&peaks.iter().map(|&t| cumulative[t]).collect::<Vec<_>>());
+    let Some(&last) = peaks.iter().rev().find(|&&t| cumulative[t] >= threshold) else {
+        return Vec::new();
+    };
+
+    let mut beats = vec![last];
+    let mut current = last;
+    while let Some(previous) = backlink[current] {
+        beats.push(previous);
+        current = previous;
+    }
+    beats.reverse();
+
+    // Drop weak beats tracked through silence at either end
+    let strengths: Vec<f64> = beats.iter().map(|&b| local[b]).collect();
+    let rms = (strengths.iter().map(|s| s * s).sum::<f64>() / strengths.len() as f64).sqrt();
+    let strong = |b: &usize| local[*b] >= 0.5 * rms;
+    let start = beats.iter().position(strong).unwrap_or(beats.len());
+    let end = beats.iter().rposition(strong).map_or(start, |i| i + 1);
+    beats[start..end].to_vec()
+}
+
+/// Least-squares slope of beat time against beat number, which is finer than
+/// the frame-quantised intervals between single beats.
+fn average_interval(beats: &[f64]) -> Option<f64> {
+    if beats.len() < 2 {
+        return None;
+    }
+    let n = beats.len() as f64;
+    let index_mean = (n - 1.0) / 2.0;
+    let time_mean = mean(beats);
+    let (covariance, variance) = beats.iter().enumerate().fold((0.0, 0.0), |(c, v), (i, t)| {
+        let di = i as f64 - index_mean;
+        (c + di * (t - time_mean), v + di * di)
+    });
+    Some(covariance / variance)
+}
+
+/// Group beats into segments of steady tempo.
+fn tempo_map(beats: &[f64]) -> Vec<TempoSegment> {
+    let intervals: Vec<f64> = beats.windows(2).map(|w| w[1] - w[0]).collect();
+    if intervals.is_empty() {
+        return Vec::new();
+    }
+    let half = LOCAL_WINDOW_BEATS / 2;
+    let local_bpm = |i: usize| {
+        let window = &intervals[i.saturating_sub(half)..(i + half).min(intervals.len())];
+        60.0 / median(window)
+    };
+
+    let mut segments = Vec::new();
+    let mut start = 0;
+    for i in 1..intervals.len() {
+        let segment_bpm = 60.0 / mean(&intervals[start..i]);
+        let long_enough = i - start >= half;
+        if long_enough && (local_bpm(i) / segment_bpm - 1.0).abs() > SEGMENT_TOLERANCE {
+            segments.push(TempoSegment {
+                start_secs: beats[start],
+                bpm: segment_bpm,
+            });
+            start = i;
+        }
+    }
+    segments.push(TempoSegment {
+        start_secs: beats[start],
+        bpm: 60.0 / mean(&intervals[start..]),
+    });
+    segments
+}
+
+/// Beat-in-bar phase whose beats carry the most low-frequency onset energy.
+fn downbeat_phase(beat_frames: &[usize], low_band: &[f64]) -> usize {
+    (0..BEATS_PER_BAR.min(beat_frames.len()))
+        .map(|phase| {
+            let strengths: Vec<f64> = beat_frames[phase..]
+                .iter()
+                .step_by(BEATS_PER_BAR)
+                .map(|&b| low_band.get(b).copied().unwrap_or(0.0))
+                .collect();
+            (phase, mean(&strengths))
+        })
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map_or(0, |(phase, _)| phase)
+}
+
+/// Detect tempo, beats and downbeats from normalized full-range and
+/// `LOW_BAND_HZ` onset envelopes.
+pub fn analyze_envelopes(envelope: &[f64], low_band: &[f64], frame_rate: f64, duration_secs: f64) -> TempoAnalysis {
+    let period = estimate_period(envelope, frame_rate);
+    let beat_frames = track_beats(envelope, period);
+    let beats: Vec<f64> = beat_frames.iter().map(|&f| f as f64 / frame_rate).collect();
+
+    let bpm = average_interval(&beats).map_or(60.0 * frame_rate / period, |interval| 60.0 / interval);
+    let downbeat_index = downbeat_phase(&beat_frames, low_band);
+
+    let mut tempo_map = tempo_map(&beats);
+    if tempo_map.is_empty() {
+        tempo_map.push(TempoSegment { start_secs: 0.0, bpm });
+    }
+
+    TempoAnalysis {
+        bpm,
+        offset_secs: beats.first().copied().unwrap_or(0.0),
+        downbeat_offset_secs: beats.get(downbeat_index).copied().unwrap_or(0.0),
+        beats,
+        downbeat_index,
+        tempo_map,
+        duration_secs,
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::spectrum;
+
+    pub(crate) const SAMPLE_RATE: u32 = 22050;
+    /// Onsets are found within a fraction of the analysis window.
+    const BEAT_TOLERANCE_SECS: f64 = 0.05;
+
+    /// Click times of a steady track starting at `start_secs`.
+    pub(crate) fn beat_times(bpm: f64, count: usize, start_secs: f64) -> Vec<f64> {
+        (0..count).map(|i| start_secs + i as f64 * 60.0 / bpm).collect()
+    }
+
+    /// A high click on every beat, with a bass thump under every fourth beat
+    /// from `downbeat`.
+    pub(crate) fn click_track(beats: &[f64], downbeat: usize) -> Vec<f32> {
+        let length = ((beats.last().unwrap() + 1.0) * SAMPLE_RATE as f64) as usize;
+        let mut samples = vec![0.0f32; length];
+        for (i, &time) in beats.iter().enumerate() {
+            let start = (time * SAMPLE_RATE as f64) as usize;
+            let thump = i % BEATS_PER_BAR == downbeat % BEATS_PER_BAR;
+            for (k, sample) in samples[start..].iter_mut().take(SAMPLE_RATE as usize / 8).enumerate() {
+                let t = k as f32 / SAMPLE_RATE as f32;
+                *sample += 0.4 * (std::f32::consts::TAU * 3000.0 * t).sin() * (-t / 0.01).exp();
+                if thump {
+                    *sample += 0.6 * (std::f32::consts::TAU * 60.0 * t).sin() * (-t / 0.05).exp();
+                }
+            }
+        }
+        samples
+    }
+
+    fn analyze(samples: &[f32]) -> TempoAnalysis {
+        let flux = spectrum::spectral_flux(samples, SAMPLE_RATE, &[(0.0, SAMPLE_RATE as f32 / 2.0), LOW_BAND_HZ]);
+        analyze_envelopes(
+            &normalize(&flux.bands[0]),
+            &normalize(&flux.bands[1]),
+            flux.frame_rate,
+            samples.len() as f64 / SAMPLE_RATE as f64,
+        )
+    }
+
+    #[test]
+    fn click_track_tempo_beats_and_downbeat() {
+        let expected = beat_times(128.0, 48, 0.5);
+        let analysis = analyze(&click_track(&expected, 2));
+
+        assert!((analysis.bpm - 128.0).abs() < 1.0, "bpm {}", analysis.bpm);
+        assert!(analysis.beats.len() >= expected.len() - 2, "{} beats", analysis.beats.len());
+        for beat in &analysis.beats {
+            let nearest = expected.iter().map(|e| (e - beat).abs()).fold(f64::MAX, f64::min);
+            assert!(nearest < BEAT_TOLERANCE_SECS, "beat at {} is {}s off", beat, nearest);
+        }
+        let downbeat = expected
+            .iter()
+            .map(|e| (e - analysis.downbeat_offset_secs).abs())
+            .enumerate()
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(i, _)| i)
+            .unwrap();
+        assert_eq!(downbeat % BEATS_PER_BAR, 2);
+        assert_eq!(analysis.tempo_map.len(), 1);
+    }
+
+    #[test]
+    fn period_follows_the_autocorrelation_peak() {
+        let frame_rate = 50.0;
+        // An onset every 25 frames is 120 BPM at 50 frames per second
+        let envelope: Vec<f64> = (0..1000).map(|i| if i % 25 == 0 { 1.0 } else { 0.0 }).collect();
+        assert!((estimate_period(&envelope, frame_rate) - 25.0).abs() < 0.5);
+        // Too short to hold a single period
+        assert_eq!(estimate_period(&envelope[..10], frame_rate), 25.0);
+    }
+
+    #[test]
+    fn tracked_beats_land_on_onsets() {
+        let envelope: Vec<f64> = (0..400).map(|i| if i % 20 == 5 { 1.0 } else { 0.0 }).collect();
+        let beats = track_beats(&envelope, 20.0);
+        assert_eq!(beats, (5..400).step_by(20).collect::<Vec<_>>());
+        assert!(track_beats(&[0.0; 100], 20.0).is_empty());
+    }
+
+    #[test]
+    fn average_interval_fits_all_beats() {
+        assert_eq!(average_interval(&[1.0]), None);
+        let interval = average_interval(&beat_times(100.0, 10, 2.0)).unwrap();
+        assert!((interval - 0.6).abs() < 1e-9);
+    }
+
+    #[test]
+    fn tempo_map_splits_at_a_tempo_change() {
+        let mut beats = beat_times(120.0, 16, 0.0);
+        let change = *beats.last().unwrap();
+        beats.extend(beat_times(150.0, 16, change).into_iter().skip(1));
+
+        let segments = tempo_map(&beats);
+        assert_eq!(segments.len(), 2, "{:?}", segments);
+        assert!((segments[0].bpm - 120.0).abs() < 0.5);
+        assert_eq!(segments[0].start_secs, 0.0);
+        assert!((segments[1].bpm - 150.0).abs() < 0.5);
+        assert!((segments[1].start_secs - change).abs() < 1e-9);
+
+        // Small jitter stays in one segment
+        let steady: Vec<f64> = beat_times(120.0, 32, 0.0)
+            .iter()
+            .enumerate()
+            .map(|(i, t)| t + if i % 2 == 0 { 0.002 } else { -0.002 })
+            .collect();
+        assert_eq!(tempo_map(&steady).len(), 1);
+        assert!(tempo_map(&[1.0]).is_empty());
+    }
+
+    #[test]
+    fn downbeat_is_the_strongest_low_phase() {
+        let frames: Vec<usize> = (0..16).map(|i| i * 10).collect();
+        let mut low_band = vec![0.0; 160];
+        for &frame in frames.iter().skip(3).step_by(BEATS_PER_BAR) {
+            low_band[frame] = 1.0;
+        }
+        assert_eq!(downbeat_phase(&frames, &low_band), 3);
+        assert_eq!(downbeat_phase(&[], &low_band), 0);
+    }
+}
@@ -0,0 +1,137 @@
+import { isTauri } from '$utils/isTauri';
+import { invokeBackend } from '$api/backend';
+import type { BeatMap, LaneMode } from '$types/rhythm.type';
+
+export interface TempoSegment {
+	startSecs: number;
+	bpm: number;
+}
+
+export interface TempoAnalysis {
+	/** Overall tempo, from the average beat interval */
+	bpm: number;
+	/** Time of the first tracked beat */
+	offsetSecs: number;
+	/** Time of the first downbeat */
+	downbeatOffsetSecs: number;
+	/** Every tracked beat, in seconds; follows tempo drift */
+	beats: number[];
+	/** Index into `beats` of the first downbeat */
+	downbeatIndex: number;
+	tempoMap: TempoSegment[];
+	durationSecs: number;
+}
+
+export interface AudioMetadata {
+	/** Decoder short name, e.g. `vorbis`, `mp3`, `flac`, `pcm_s16le` */
+	codec: string;
+	sampleRate: number;
+	channels: number;
+	/** Samples per channel */
+	frames: number;
+	durationSecs: number;
+}
+
+export interface Spectrogram {
+	columns: number;
+	/** Log-spaced frequency bands from `minHz` (row 0) to `maxHz` */
+	rows: number;
+	minHz: number;
+	maxHz: number;
+	/** Level per cell from 0 (silent) to 255 (loudest), column by column */
+	values: number[];
+}
+
+export interface AudioPreview {
+	format: number;
+	durationSecs: number;
+	sampleRate: number;
+	/** Lowest and highest sample per column, in -1..1 */
+	peaksMin: number[];
+	peaksMax: number[];
+	spectrogram?: Spectrogram;
+}
+
+export interface PreviewOptions {
+	/** Peak columns, 16 to 8192 (default 1024) */
+	width?: number;
+	spectrogram?: boolean;
+}
+
+export type ChartDifficulty = 'easy' | 'normal' | 'hard' | 'expert';
+
+export interface GeneratedChart {
+	/** Notes already laid out for the requested lane count */
+	beatMap: BeatMap;
+	tempo: TempoAnalysis;
+}
+
+async function getInvoke() {
+	if (!isTauri()) throw new Error('Audio analysis requires the desktop app.');
+	return invokeBackend;
+}
+
+/** The file's bytes as a raw invoke body, with its extension as a format hint */
+async function rawAudio(file: File) {
+	const extension = file.name.split('.').pop() ?? '';
+	return {
+		body: new Uint8Array(await file.arrayBuffer()),
+		options: { headers: { 'x-audio-extension': extension } }
+	};
+}
+
+/**
+ * Audio API - decoding and analysis done by the Rust backend (desktop only)
+ */
+export const audioApi = {
+	/** Decode a user-picked file and report its format and exact duration */
+	async probe(file: File): Promise<AudioMetadata> {
+		const invoke = await getInvoke();
+		const { body, options } = await rawAudio(file);
+		return invoke<AudioMetadata>('audio_probe', body, options);
+	},
+
+	/** Decode a downloaded map's audio and report its format and exact duration */
+	async probeMap(mapId: string): Promise<AudioMetadata> {
+		const invoke = await getInvoke();
+		return invoke<AudioMetadata>('audio_probe_map', { mapId });
+	},
+
+	/** Waveform peaks (and optionally a spectrogram) of a user-picked file */
+	async preview(file: File, { width, spectrogram = false }: PreviewOptions = {}): Promise<AudioPreview> {
+		const invoke = await getInvoke();
+		const { body, options } = await rawAudio(file);
+		const headers: Record<string, string> = {
+			...options.headers,
+			'x-preview-spectrogram': String(spectrogram)
+		};
+		if (width !== undefined) headers['x-preview-width'] = String(width);
+		return invoke<AudioPreview>('audio_preview', body, { headers });
+	},
+
+	/** Waveform peaks (and optionally a spectrogram) of a downloaded map, cached beside it */
+	async previewMap(
+		mapId: string,
+		{ width, spectrogram = false }: PreviewOptions = {}
+	): Promise<AudioPreview> {
+		const invoke = await getInvoke();
+		return invoke<AudioPreview>('audio_preview_map', { mapId, width, spectrogram });
+	},
+
+	/** Generate a chart from the file's onsets for a difficulty and lane count */
+	async generateChart(
+		file: File,
+		difficulty: ChartDifficulty,
+		lanes: LaneMode
+	): Promise<GeneratedChart> {
+		const invoke = await getInvoke();
+		const { body, options } = await rawAudio(file);
+		return invoke<GeneratedChart>('chart_generate', body, {
+			headers: {
+				...options.headers,
+				'x-chart-difficulty': difficulty,
+				'x-chart-lanes': String(lanes)
+			}
+		});
+	}
+};
@@ -0,0 +1,69 @@
+import type { InvokeArgs, InvokeOptions } from '@tauri-apps/api/core';
+
+export type BackendErrorKind =
+	| 'network'
+	| 'offline'
+	| 'http_status'
+	| 'not_found'
+	| 'rate_limited'
+	| 'invalid_response'
+	| 'zip_invalid'
+	| 'invalid_beatmap'
+	| 'zip_limit_exceeded'
+	| 'missing_audio'
+	| 'missing_info_dat'
+	| 'hash_mismatch'
+	| 'io'
+	| 'audio_decode'
+	| 'cache_corrupt'
+	| 'invalid_argument'
+	| 'internal';
+
+/** Structured error returned by the Rust backend commands */
+export interface BackendError {
+	kind: BackendErrorKind;
+	message: string;
+	status?: number;
+	retryAfterSecs?: number | null;
+	/** zip_limit_exceeded: the ZipLimitsSettings field that was exceeded */
+	limit?: string;
+	/** hash_mismatch: the requested version hash and the one computed from the files */
+	expected?: string;
+	actual?: string;
+}
+
+/** Error thrown by the API modules when a backend command fails */
+export class BackendApiError extends Error {
+	kind: BackendErrorKind;
+	details: BackendError;
+
+	constructor(details: BackendError) {
+		super(details.message);
+		this.name = 'BackendApiError';
+		this.kind = details.kind;
+		this.details = details;
+	}
+}
+
+export function isBackendError(value: unknown): value is BackendError {
+	return (
+		typeof value === 'object' &&
+		value !== null &&
+		typeof (value as BackendError).kind === 'string' &&
+		typeof (value as BackendError).message === 'string'
+	);
+}
+
+/** Invoke a backend command, rethrowing its structured error as a BackendApiError */
+export async function invokeBackend<T>(
+	cmd: string,
+	args?: InvokeArgs,
+	options?: InvokeOptions
+): Promise<T> {
+	const { invoke } = await import('@tauri-apps/api/core');
+	try {
+		return await invoke<T>(cmd, args, options);
+	} catch (e) {
+		throw isBackendError(e) ? new BackendApiError(e) : e;
+	}
+}
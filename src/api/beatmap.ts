@@ -0,0 +1,32 @@
+import { isTauri } from '$utils/isTauri';
+import { invokeBackend } from '$api/backend';
+import type { BeatMap, BeatMapInfo } from '$types/rhythm.type';
+
+/** Info.dat normalized by the backend from the v2 or v4 layout */
+export interface MapInfo extends BeatMapInfo {
+	format: 'v2' | 'v4';
+	songFilename: string;
+	coverFilename: string | null;
+	audioDataFilename: string | null;
+}
+
+async function getInvoke() {
+	if (!isTauri()) throw new Error('Beatmap parsing requires the desktop app.');
+	return invokeBackend;
+}
+
+/**
+ * Beatmap API - Info.dat and difficulty files parsed by the Rust backend (desktop only)
+ */
+export const beatmapApi = {
+	async info(mapId: string): Promise<MapInfo> {
+		const invoke = await getInvoke();
+		return invoke<MapInfo>('beatmap_info', { mapId });
+	},
+
+	/** Parse a difficulty file into notes and obstacles timed in seconds */
+	async parse(mapId: string, filename: string): Promise<BeatMap> {
+		const invoke = await getInvoke();
+		return invoke<BeatMap>('beatmap_parse', { mapId, filename });
+	}
+};
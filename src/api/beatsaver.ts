@@ -1,10 +1,12 @@
 import { isTauri } from '$utils/isTauri';
+import { invokeBackend, type BackendError } from '$api/backend';
 import { unzipSync } from 'fflate';
 import type {
 	BeatSaverSearchResponse,
 	BeatSaverMap,
 	BeatSaverMapExtracted,
-	BeatSaverSearchFilters
+	BeatSaverSearchFilters,
+	CachedMapManifest
 } from '$types/rhythm.type';
 import { DEFAULT_SEARCH_FILTERS } from '$types/rhythm.type';
 import {
@@ -12,19 +14,21 @@ import {
 	beatsaverBrowseCacheService
 } from '$services/beatsaver-cache.service';
 
+/**
+ * Download cache state of a map relative to its latest version. `unverifiable`
+ * is a current copy whose files can't be checked against the version hash
+ * (Info.dat v4 maps).
+ */
+export type DownloadStatus = 'missing' | 'cached' | 'outdated' | 'unverifiable';
+
 export interface TrackFetchResult {
 	mapId: string;
 	status: 'success' | 'already_cached' | 'error';
-	error: string | null;
+	error: BackendError | null;
 }
 
 const API_BASE = 'https://api.beatsaver.com';
 
-async function getInvoke() {
-	const { invoke } = await import('@tauri-apps/api/core');
-	return invoke;
-}
-
 function buildSearchParams(query: string, filters: BeatSaverSearchFilters): string {
 	const params = new URLSearchParams();
 
@@ -56,12 +60,20 @@ function buildSearchParams(query: string, filters: BeatSaverSearchFilters): stri
 	return params.toString();
 }
 
-function bytesToBase64(bytes: Uint8Array): string {
-	let binary = '';
-	for (let i = 0; i < bytes.length; i++) {
-		binary += String.fromCharCode(bytes[i]);
-	}
-	return btoa(binary);
+function bytesToObjectUrl(bytes: Uint8Array): string {
+	return URL.createObjectURL(new Blob([bytes]));
+}
+
+/** URL of a file in the Rust download cache, served by the moontapper:// protocol */
+export async function mapFileUrl(mapId: string, file: string): Promise<string> {
+	const { convertFileSrc } = await import('@tauri-apps/api/core');
+	return convertFileSrc(`map/${mapId}/${file}`, 'moontapper');
+}
+
+async function fetchMapText(mapId: string, file: string): Promise<string> {
+	const res = await fetch(await mapFileUrl(mapId, file));
+	if (!res.ok) throw new Error(`Failed to load ${file}: HTTP ${res.status}`);
+	return res.text();
 }
 
 function extractMapZip(zipBytes: Uint8Array): BeatSaverMapExtracted {
@@ -69,8 +81,8 @@ function extractMapZip(zipBytes: Uint8Array): BeatSaverMapExtracted {
 
 	let info_dat = '';
 	const beatmaps: Record<string, string> = {};
-	let audio_base64 = '';
-	let cover_base64: string | null = null;
+	let audio_url = '';
+	let cover_url: string | null = null;
 
 	const decoder = new TextDecoder();
 
@@ -81,16 +93,16 @@ function extractMapZip(zipBytes: Uint8Array): BeatSaverMapExtracted {
 		} else if (name.endsWith('.dat')) {
 			beatmaps[path] = decoder.decode(data);
 		} else if (name.endsWith('.ogg') || name.endsWith('.egg')) {
-			audio_base64 = bytesToBase64(data);
+			audio_url = bytesToObjectUrl(data);
 		} else if (name.endsWith('.jpg') || name.endsWith('.jpeg') || name.endsWith('.png')) {
-			cover_base64 = bytesToBase64(data);
+			cover_url = bytesToObjectUrl(data);
 		}
 	}
 
 	if (!info_dat) throw new Error('No Info.dat found in ZIP');
-	if (!audio_base64) throw new Error('No audio file found in ZIP');
+	if (!audio_url) throw new Error('No audio file found in ZIP');
 
-	return { info_dat, beatmaps, audio_base64, cover_base64 };
+	return { info_dat, beatmaps, audio_url, audio_fallback_url: null, cover_url };
 }
 
 /** Cache a map in localStorage (add or update) */
@@ -117,8 +129,7 @@ export const beatsaverApi = {
 	): Promise<BeatSaverSearchResponse> {
 		let result: BeatSaverSearchResponse;
 		if (isTauri()) {
-			const invoke = await getInvoke();
-			result = await invoke<BeatSaverSearchResponse>('beatsaver_search', {
+			result = await invokeBackend<BeatSaverSearchResponse>('beatsaver_search', {
 				query,
 				page,
 				filters
@@ -154,8 +165,7 @@ export const beatsaverApi = {
 		// Cache miss — fetch from API
 		let result: BeatSaverSearchResponse;
 		if (isTauri()) {
-			const invoke = await getInvoke();
-			result = await invoke<BeatSaverSearchResponse>('beatsaver_browse', {
+			result = await invokeBackend<BeatSaverSearchResponse>('beatsaver_browse', {
 				sort,
 				pageSize
 			});
@@ -188,8 +198,7 @@ export const beatsaverApi = {
 		// Cache miss — fetch from API
 		let map: BeatSaverMap;
 		if (isTauri()) {
-			const invoke = await getInvoke();
-			map = await invoke<BeatSaverMap>('beatsaver_get_map', { id });
+			map = await invokeBackend<BeatSaverMap>('beatsaver_get_map', { id });
 		} else {
 			const res = await fetch(`${API_BASE}/maps/id/${encodeURIComponent(id)}`);
 			if (!res.ok) throw new Error(`Map fetch failed: HTTP ${res.status}`);
@@ -200,19 +209,50 @@ export const beatsaverApi = {
 		return map;
 	},
 
+	/**
+	 * Whether the latest version of a map is downloaded. Without a version hash
+	 * the backend compares against the latest version in its metadata cache.
+	 */
+	async downloadStatus(mapId: string, versionHash: string | null = null): Promise<DownloadStatus> {
+		if (!isTauri()) return 'missing';
+		return invokeBackend<DownloadStatus>('beatsaver_has_download', { mapId, versionHash });
+	},
+
+	/** True only when the cached copy is current; outdated maps need a re-download */
 	async hasDownloadedMap(mapId: string): Promise<boolean> {
-		if (!isTauri()) return false;
-		const invoke = await getInvoke();
-		return invoke<boolean>('beatsaver_has_download', { mapId });
+		const status = await this.downloadStatus(mapId);
+		return status === 'cached' || status === 'unverifiable';
+	},
+
+	/**
+	 * Free the blob: URLs the web build creates for a downloaded track. Call
+	 * once the track is no longer played; moontapper:// URLs need no cleanup.
+	 */
+	releaseTrack(extracted: BeatSaverMapExtracted): void {
+		for (const url of [extracted.audio_url, extracted.cover_url]) {
+			if (url?.startsWith('blob:')) URL.revokeObjectURL(url);
+		}
 	},
 
 	async downloadTrack(mapId: string, downloadUrl: string): Promise<BeatSaverMapExtracted> {
 		if (isTauri()) {
-			const invoke = await getInvoke();
-			return invoke<BeatSaverMapExtracted>('beatsaver_download_track', {
+			const manifest = await invokeBackend<CachedMapManifest>('beatsaver_download_track', {
 				mapId,
 				downloadUrl
 			});
+			const diffs = await Promise.all(
+				manifest.beatmapFilenames.map(
+					async (name) => [name, await fetchMapText(mapId, `diff/${name}`)] as const
+				)
+			);
+			return {
+				info_dat: await fetchMapText(mapId, 'info'),
+				beatmaps: Object.fromEntries(diffs),
+				audio_url: await mapFileUrl(mapId, 'audio'),
+				audio_fallback_url: await mapFileUrl(mapId, 'audio.wav'),
+				cover_url: manifest.coverFilename ? await mapFileUrl(mapId, 'cover') : null,
+				outdated: manifest.outdated
+			};
 		}
 		const res = await fetch(downloadUrl);
 		if (!res.ok) throw new Error(`Download failed: HTTP ${res.status}`);
@@ -223,7 +263,6 @@ export const beatsaverApi = {
 	/** Enqueue a track for background download. Returns mapId for event correlation. */
 	async fetchTrack(mapId: string, downloadUrl: string): Promise<string> {
 		if (!isTauri()) throw new Error('Background download requires the desktop app.');
-		const invoke = await getInvoke();
-		return invoke<string>('beatsaver_fetch_track', { mapId, downloadUrl });
+		return invokeBackend<string>('beatsaver_fetch_track', { mapId, downloadUrl });
 	}
 };
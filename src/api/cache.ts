@@ -0,0 +1,78 @@
+import { isTauri } from '$utils/isTauri';
+import { invokeBackend } from '$api/backend';
+import type { BackendError } from '$api/backend';
+
+export interface CacheEntrySize {
+	mapId: string;
+	sizeBytes: number;
+}
+
+export interface CacheStats {
+	totalBytes: number;
+	mapCount: number;
+	/** 0 means unlimited */
+	quotaBytes: number;
+	/** Maps kept regardless of quota because they are in a playlist */
+	pinnedCount: number;
+	largest: CacheEntrySize[];
+}
+
+export interface CachedMapInfo {
+	mapId: string;
+	sizeBytes: number;
+	/** Unix seconds */
+	downloadedAt: number;
+	lastPlayedAt: number | null;
+	versionHash: string | null;
+	/** In a playlist, so never evicted by the quota */
+	pinned: boolean;
+}
+
+export interface CorruptCacheEntry {
+	mapId: string;
+	error: BackendError;
+}
+
+export interface CacheVerifyResult {
+	checked: number;
+	corrupt: CorruptCacheEntry[];
+	/** Intact maps whose files can't be checked against their version hash */
+	unverifiable: string[];
+}
+
+async function getInvoke() {
+	if (!isTauri()) throw new Error('The download cache requires the desktop app.');
+	return invokeBackend;
+}
+
+/**
+ * Cache API - downloaded maps stored by the Rust backend (desktop only)
+ */
+export const cacheApi = {
+	async stats(): Promise<CacheStats> {
+		const invoke = await getInvoke();
+		return invoke<CacheStats>('cache_stats');
+	},
+
+	async list(): Promise<CachedMapInfo[]> {
+		const invoke = await getInvoke();
+		return invoke<CachedMapInfo[]>('cache_list');
+	},
+
+	async delete(mapId: string): Promise<void> {
+		const invoke = await getInvoke();
+		await invoke('cache_delete', { mapId });
+	},
+
+	/** Remove every downloaded map. Returns the number of entries removed. */
+	async purge(): Promise<number> {
+		const invoke = await getInvoke();
+		return invoke<number>('cache_purge');
+	},
+
+	/** Re-open every entry and report corrupt ones without deleting anything */
+	async verify(): Promise<CacheVerifyResult> {
+		const invoke = await getInvoke();
+		return invoke<CacheVerifyResult>('cache_verify');
+	}
+};
@@ -0,0 +1,31 @@
+import { isTauri } from '$utils/isTauri';
+import { invokeBackend } from '$api/backend';
+import type { BeatMap } from '$types/rhythm.type';
+
+export type BombPolicy = 'keep' | 'drop';
+export type ObstaclePolicy = 'keep' | 'drop';
+
+export interface ChartTransformOptions {
+	/** Target lane count, 1 to 4 */
+	lanes: number;
+	/** Minimum time between consecutive notes in one lane; 0 disables */
+	minGapSecs?: number;
+	bombs?: BombPolicy;
+	obstacles?: ObstaclePolicy;
+}
+
+async function getInvoke() {
+	if (!isTauri()) throw new Error('Chart transforms require the desktop app.');
+	return invokeBackend;
+}
+
+/**
+ * Chart API - lane condensing done by the Rust backend (desktop only)
+ */
+export const chartApi = {
+	/** Condense a 4-column chart to fewer lanes, removing stacked notes */
+	async transform(beatMap: BeatMap, options: ChartTransformOptions): Promise<BeatMap> {
+		const invoke = await getInvoke();
+		return invoke<BeatMap>('chart_transform', { beatmap: beatMap, options });
+	}
+};
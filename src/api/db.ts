@@ -1,4 +1,5 @@
 import { isTauri } from '$utils/isTauri';
+import { invokeBackend } from '$api/backend';
 
 /** Result from a SELECT query */
 export interface QueryResult<T = Record<string, unknown>> {
@@ -12,6 +13,12 @@ export interface ExecuteResult {
 	last_insert_id: number | null;
 }
 
+/** Applied schema version vs. the latest migration known to the backend */
+export interface SchemaVersion {
+	current: number;
+	latest: number;
+}
+
 /** Raw query result before transformation */
 interface RawQueryResult {
 	columns: string[];
@@ -49,8 +56,7 @@ export const db = {
 	): Promise<QueryResult<T>> {
 		if (!isTauri()) return { columns: [], rows: [] };
 
-		const { invoke } = await import('@tauri-apps/api/core');
-		const result = await invoke<RawQueryResult>('db_query', { sql, params });
+		const result = await invokeBackend<RawQueryResult>('db_query', { sql, params });
 
 		// Transform array rows to objects using column names
 		const rows = result.rows.map((row) => {
@@ -86,8 +92,15 @@ export const db = {
 	 */
 	async execute(sql: string, params: unknown[] = []): Promise<ExecuteResult> {
 		if (!isTauri()) return { rows_affected: 0, last_insert_id: null };
-		const { invoke } = await import('@tauri-apps/api/core');
-		return invoke<ExecuteResult>('db_execute', { sql, params });
+		return invokeBackend<ExecuteResult>('db_execute', { sql, params });
+	},
+
+	/**
+	 * Report the schema version applied by the backend migrations
+	 */
+	async schemaVersion(): Promise<SchemaVersion> {
+		if (!isTauri()) return { current: 0, latest: 0 };
+		return invokeBackend<SchemaVersion>('db_schema_version');
 	}
 };
 
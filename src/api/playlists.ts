@@ -0,0 +1,67 @@
+import { isTauri } from '$utils/isTauri';
+import { invokeBackend } from '$api/backend';
+import type { PlaylistTrack, RhythmPlaylist } from '$types/rhythm.type';
+
+export interface PlaylistImportResult {
+	alreadyImported: boolean;
+	playlists: number;
+	tracks: number;
+}
+
+async function getInvoke() {
+	if (!isTauri()) throw new Error('Backend playlists require the desktop app.');
+	return invokeBackend;
+}
+
+/**
+ * Playlists API - backend playlist store (desktop only)
+ */
+export const playlistsApi = {
+	async list(): Promise<RhythmPlaylist[]> {
+		const invoke = await getInvoke();
+		return invoke<RhythmPlaylist[]>('playlists_list');
+	},
+
+	async create(name: string): Promise<RhythmPlaylist> {
+		const invoke = await getInvoke();
+		return invoke<RhythmPlaylist>('playlists_create', { name });
+	},
+
+	async rename(id: string, name: string): Promise<RhythmPlaylist> {
+		const invoke = await getInvoke();
+		return invoke<RhythmPlaylist>('playlists_rename', { id, name });
+	},
+
+	async reorder(ids: string[]): Promise<void> {
+		const invoke = await getInvoke();
+		return invoke<void>('playlists_reorder', { ids });
+	},
+
+	async remove(id: string): Promise<void> {
+		const invoke = await getInvoke();
+		return invoke<void>('playlists_delete', { id });
+	},
+
+	async addTrack(playlistId: string, track: PlaylistTrack): Promise<RhythmPlaylist> {
+		const invoke = await getInvoke();
+		return invoke<RhythmPlaylist>('playlists_add_track', { playlistId, track });
+	},
+
+	async removeTrack(playlistId: string, trackId: string): Promise<RhythmPlaylist> {
+		const invoke = await getInvoke();
+		return invoke<RhythmPlaylist>('playlists_remove_track', { playlistId, trackId });
+	},
+
+	async reorderTracks(playlistId: string, trackIds: string[]): Promise<RhythmPlaylist> {
+		const invoke = await getInvoke();
+		return invoke<RhythmPlaylist>('playlists_reorder_tracks', { playlistId, trackIds });
+	},
+
+	/** One-shot import of the locally persisted playlists. Later calls are no-ops. */
+	async importLocal(playlists: RhythmPlaylist[]): Promise<PlaylistImportResult> {
+		const invoke = await getInvoke();
+		return invoke<PlaylistImportResult>('playlists_import_local_storage', {
+			json: JSON.stringify(playlists)
+		});
+	}
+};
@@ -0,0 +1,43 @@
+import { isTauri } from '$utils/isTauri';
+import { invokeBackend } from '$api/backend';
+import type { LaneMode, RhythmScore, RhythmScoreSubmitResult } from '$types/rhythm.type';
+
+/**
+ * Scores API - backend score store (desktop only)
+ *
+ * Scores are keyed by mapId + difficulty + lane mode. In the web build these
+ * calls resolve to empty results and localStorage remains the only store.
+ */
+export const scoresApi = {
+	async submit(score: RhythmScore): Promise<RhythmScoreSubmitResult | null> {
+		if (!isTauri()) return null;
+		return invokeBackend<RhythmScoreSubmitResult>('scores_submit', { score });
+	},
+
+	async forMap(
+		mapId: string,
+		difficulty: string | null = null,
+		laneMode: LaneMode | null = null
+	): Promise<RhythmScore[]> {
+		if (!isTauri()) return [];
+		return invokeBackend<RhythmScore[]>('scores_for_map', { mapId, difficulty, laneMode });
+	},
+
+	async personalBest(
+		mapId: string,
+		difficulty: string,
+		laneMode: LaneMode
+	): Promise<RhythmScore | null> {
+		if (!isTauri()) return null;
+		return invokeBackend<RhythmScore | null>('scores_personal_best', {
+			mapId,
+			difficulty,
+			laneMode
+		});
+	},
+
+	async recent(limit: number = 50): Promise<RhythmScore[]> {
+		if (!isTauri()) return [];
+		return invokeBackend<RhythmScore[]>('scores_recent', { limit });
+	}
+};
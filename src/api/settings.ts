@@ -0,0 +1,72 @@
+import { isTauri } from '$utils/isTauri';
+import { invokeBackend } from '$api/backend';
+
+export interface BeatSaverMirror {
+	apiBase: string;
+	cdnBase: string | null;
+}
+
+export interface BeatSaverEndpointSettings {
+	apiBase: string;
+	cdnBase: string | null;
+	mirrors: BeatSaverMirror[];
+}
+
+export interface HttpSettings {
+	connectTimeoutSecs: number;
+	readTimeoutSecs: number;
+	proxy: string | null;
+	maxAttempts: number;
+	retryBaseDelayMs: number;
+	retryMaxDelayMs: number;
+	rateLimitPerSec: number;
+	rateLimitBurst: number;
+}
+
+export interface MetadataCacheSettings {
+	mapTtlSecs: number;
+	queryTtlSecs: number;
+}
+
+export interface DownloadCacheSettings {
+	/** Disk quota for downloaded maps in bytes; 0 disables eviction */
+	maxBytes: number;
+}
+
+/** Limits enforced while extracting map ZIPs */
+export interface ZipLimitsSettings {
+	maxEntries: number;
+	maxTotalBytes: number;
+	/** Applied to entries larger than 1 MiB */
+	maxCompressionRatio: number;
+	maxAudioBytes: number;
+}
+
+/** Settings owned and persisted by the Rust backend */
+export interface BackendSettings {
+	beatsaver: BeatSaverEndpointSettings;
+	http: HttpSettings;
+	/** Answer BeatSaver requests from the local cache only */
+	offline: boolean;
+	metadataCache: MetadataCacheSettings;
+	downloadCache: DownloadCacheSettings;
+	zipLimits: ZipLimitsSettings;
+}
+
+async function getInvoke() {
+	if (!isTauri()) throw new Error('Backend settings require the desktop app.');
+	return invokeBackend;
+}
+
+export const settingsApi = {
+	async get(): Promise<BackendSettings> {
+		const invoke = await getInvoke();
+		return invoke<BackendSettings>('settings_get');
+	},
+
+	/** Validate, persist and apply new settings. Returns the normalized settings. */
+	async set(settings: BackendSettings): Promise<BackendSettings> {
+		const invoke = await getInvoke();
+		return invoke<BackendSettings>('settings_set', { settings });
+	}
+};
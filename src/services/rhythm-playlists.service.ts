@@ -1,11 +1,68 @@
 import { ArrayServiceClass } from '$services/classes/array-service.class';
-import type { RhythmPlaylist } from '$types/rhythm.type';
+import { playlistsApi } from '$api/playlists';
+import { isTauri } from '$utils/isTauri';
+import type { PlaylistTrack, RhythmPlaylist } from '$types/rhythm.type';
 import { FAVORITES_PLAYLIST_ID } from '$types/rhythm.type';
 
-export const rhythmPlaylistsService = new ArrayServiceClass<RhythmPlaylist>(
-	'rhythm-playlists',
-	[]
-);
+/**
+ * Playlists. The desktop app keeps them in the backend database and the store
+ * mirrors it; the browser build keeps them in localStorage only.
+ */
+class RhythmPlaylistsService extends ArrayServiceClass<RhythmPlaylist> {
+	/** Move the localStorage playlists into the backend (once), then load them from it. */
+	async init(): Promise<void> {
+		if (!isTauri()) return;
+		await playlistsApi.importLocal(this.all());
+		this.store.set(await playlistsApi.list());
+	}
+
+	async create(name: string): Promise<RhythmPlaylist> {
+		if (isTauri()) return this.add(await playlistsApi.create(name));
+		const now = new Date().toISOString();
+		return this.add({ id: crypto.randomUUID(), name, tracks: [], createdAt: now, updatedAt: now });
+	}
+
+	async rename(playlist: RhythmPlaylist, name: string): Promise<RhythmPlaylist> {
+		if (isTauri()) return this.update(await playlistsApi.rename(playlist.id, name));
+		return this.update({ ...playlist, name, updatedAt: new Date().toISOString() });
+	}
+
+	async delete(playlist: RhythmPlaylist): Promise<void> {
+		if (isTauri()) await playlistsApi.remove(playlist.id);
+		this.remove(playlist);
+	}
+
+	async addTrack(playlist: RhythmPlaylist, track: PlaylistTrack): Promise<RhythmPlaylist> {
+		if (isTauri()) return this.update(await playlistsApi.addTrack(playlist.id, track));
+		if (playlist.tracks.some((t) => t.id === track.id)) return playlist;
+		return this.update({
+			...playlist,
+			tracks: [...playlist.tracks, track],
+			updatedAt: new Date().toISOString()
+		});
+	}
+
+	async removeTrack(playlist: RhythmPlaylist, trackId: string): Promise<RhythmPlaylist> {
+		if (isTauri()) return this.update(await playlistsApi.removeTrack(playlist.id, trackId));
+		return this.update({
+			...playlist,
+			tracks: playlist.tracks.filter((t) => t.id !== trackId),
+			updatedAt: new Date().toISOString()
+		});
+	}
+
+	/** Add a playlist read from an export file. */
+	async importPlaylist(playlist: RhythmPlaylist): Promise<RhythmPlaylist> {
+		if (!isTauri()) return this.add(playlist);
+		let created = await this.create(playlist.name);
+		for (const track of playlist.tracks) {
+			created = await this.addTrack(created, track);
+		}
+		return created;
+	}
+}
+
+export const rhythmPlaylistsService = new RhythmPlaylistsService('rhythm-playlists', []);
 
 // Ensure the default Favorites playlist always exists
 if (!rhythmPlaylistsService.exists(FAVORITES_PLAYLIST_ID)) {
@@ -158,8 +158,33 @@ export interface BeatSaverDiff {
 export interface BeatSaverMapExtracted {
 	info_dat: string;
 	beatmaps: Record<string, string>;
-	audio_base64: string;
-	cover_base64: string | null;
+	/** moontapper:// URL in the desktop app, blob: URL in the browser */
+	audio_url: string;
+	/** WAV transcode of the audio (desktop app), for when the webview can't decode it */
+	audio_fallback_url: string | null;
+	cover_url: string | null;
+	/** Desktop app only: an older cached version of the map was loaded */
+	outdated?: boolean;
+}
+
+/** Manifest of a map in the Rust download cache */
+export interface CachedMapManifest {
+	format: number;
+	mapId: string;
+	audioFilename: string;
+	coverFilename: string | null;
+	beatmapFilenames: string[];
+	downloadedAt: number;
+	sizeBytes: number;
+	versionHash: string | null;
+	/** versionHash was computed from Info.dat and the difficulty files */
+	hashVerified: boolean;
+	/** Info.dat v4 map, whose files can't be checked against versionHash */
+	hashUnverifiable: boolean;
+	/** Missing or unreferenced files noticed while resolving Info.dat */
+	warnings: string[];
+	/** An older cached version, served because the requested one couldn't be downloaded */
+	outdated: boolean;
 }
 
 // --- Parsed beat map types ---
@@ -318,6 +343,7 @@ export interface RhythmScore {
 	mapId: string;
 	mapName: string;
 	difficulty: string;
+	laneMode?: LaneMode;
 	score: number;
 	maxCombo: number;
 	perfect: number;
@@ -327,6 +353,22 @@ export interface RhythmScore {
 	date: string;
 }
 
+/** Per-field difference between a score and the previous personal best */
+export interface RhythmScoreDelta {
+	score: number;
+	maxCombo: number;
+	perfect: number;
+	good: number;
+	miss: number;
+}
+
+export interface RhythmScoreSubmitResult {
+	score: RhythmScore;
+	previousBest: RhythmScore | null;
+	isPersonalBest: boolean;
+	delta: RhythmScoreDelta | null;
+}
+
 // --- Lane mode ---
 
 export type LaneMode = 2 | 3 | 4;
@@ -1,4 +1,6 @@
-import type { BeatMap } from '$types/rhythm.type';
+import { isTauri } from '$utils/isTauri';
+import { chartApi } from '$api/chart';
+import type { BeatMap, LaneMode } from '$types/rhythm.type';
 
 const COLUMN_TO_LANE_3: Record<number, number> = { 0: 0, 1: 1, 2: 1, 3: 2 };
 const COLUMN_TO_LANE_2: Record<number, number> = { 0: 0, 1: 0, 2: 1, 3: 1 };
@@ -32,3 +34,14 @@ export function condenseTo2Lanes(beatMap: BeatMap): BeatMap {
 		}))
 	};
 }
+
+/**
+ * Condense a chart to the given lane mode. The desktop app uses the backend
+ * transform, which also removes notes stacked on the same lane and time.
+ */
+export async function condenseLanes(beatMap: BeatMap, laneMode: LaneMode): Promise<BeatMap> {
+	if (isTauri()) return chartApi.transform(beatMap, { lanes: laneMode });
+	if (laneMode === 2) return condenseTo2Lanes(beatMap);
+	if (laneMode === 3) return condenseTo3Lanes(beatMap);
+	return beatMap;
+}